@@ -0,0 +1,90 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use quiz_core::{
+    clip,
+    playlist::{Row, YOUTUBE_PREFIX},
+};
+
+/// Tools and format clips are prepared with, named as the keys of the bot's configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tools {
+    pub yt_dlp: String,
+    pub ffmpeg: String,
+    pub ytdl_format: String,
+}
+
+impl Default for Tools {
+    fn default() -> Self {
+        Self {
+            yt_dlp: clip::DEFAULT_YT_DLP.to_owned(),
+            ffmpeg: clip::DEFAULT_FFMPEG.to_owned(),
+            ytdl_format: clip::DEFAULT_FORMAT.to_owned(),
+        }
+    }
+}
+
+/// A clip cut the way the bot prepares a track.
+pub struct Clip {
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub duration: Option<i64>,
+}
+
+/// Downloads `row` with yt-dlp and cuts it with ffmpeg into `dir`, named after its `index`.
+pub fn write(row: &Row, index: usize, dir: &Path, tools: &Tools) -> Result<Clip, String> {
+    let template = dir.join(format!("{}.%(ext)s", row.video_id));
+    let url = format!("{}{}", YOUTUBE_PREFIX, row.video_id);
+    let ytdl = run(&tools.yt_dlp, &clip::download_args(&url, &tools.ytdl_format, &template.to_string_lossy()))?;
+    let output: serde_json::Value = String::from_utf8_lossy(&ytdl)
+        .lines()
+        .find(|line| !line.is_empty())
+        .ok_or_else(|| format!("no results found for '{}'", url))
+        .and_then(|line| serde_json::from_str(line).map_err(|e| e.to_string()))?;
+    let audio_ext = output["audio_ext"].as_str()
+        .ok_or_else(|| format!("no audio extension for '{}'", url))?;
+    let download = dir.join(format!("{}.{}", row.video_id, audio_ext));
+
+    let path = dir.join(format!("{:02}_{}.webm", index + 1, row.video_id));
+    let mut duration = output["duration"].as_f64().map(|duration| duration as i64);
+    let mut args = clip::input_args(&download.to_string_lossy());
+    if row.start != 0 || row.duration != 0 {
+        duration = clip::length(row.duration, duration);
+        args.extend(clip::output_args(&path.to_string_lossy(), row.start, duration));
+    } else {
+        args.extend(clip::output_args(&path.to_string_lossy(), 0, None));
+    }
+    run(&tools.ffmpeg, &args)?;
+    // only the clip is kept
+    let _ = fs::remove_file(download);
+
+    Ok(Clip {
+        path,
+        title: output["title"].as_str().map(str::to_owned),
+        duration,
+    })
+}
+
+/// Runs `command`, returning its stdout.
+fn run(command: &str, args: &[String]) -> Result<Vec<u8>, String> {
+    let output = Command::new(command)
+        .args(args)
+        .output()
+        .map_err(|e| if e.kind() == ErrorKind::NotFound {
+            format!("could not find executable '{}' on path", command)
+        } else {
+            e.to_string()
+        })?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed with non-zero status code: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    Ok(output.stdout)
+}
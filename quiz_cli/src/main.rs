@@ -0,0 +1,313 @@
+//! Plays a quiz from a `/playlist` csv in the terminal, without Discord.
+//!
+//! Guesses of one or more simulated players are read from stdin and judged by `quiz_core`,
+//! the same rules the bot runs. Nothing is played; with `--clips` the clips are written to disk.
+
+mod clips;
+
+use clips::Tools;
+
+use std::{
+    env,
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+
+use quiz_core::{
+    answer::Verdict,
+    clock::SystemClock,
+    game::{Event, Game},
+    playlist::{self, Row},
+    session::{Session, Song},
+};
+
+const USAGE: &str = "usage: quiz-cli <playlist.csv> [--player <name>]... [--clips <dir>]
+                [--yt-dlp <command>] [--ffmpeg <command>] [--ytdl-format <format>]
+
+With one player every line is a guess, with several a line is `<name>: <guess>`.
+/skip moves to the next song, /score prints the scores and /quit ends the game.
+Rows without a keyword need --clips, the title yt-dlp reports is their answer.
+The tools and format are the bot's, also read from QUIZ_BOT_YT_DLP, QUIZ_BOT_FFMPEG
+and QUIZ_BOT_YTDL_FORMAT.";
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    playlist: PathBuf,
+    players: Vec<String>,
+    // where to write the clips, which are not written without it
+    clips: Option<PathBuf>,
+    tools: Tools,
+}
+
+/// Reads the command line `args` after the program name, the tools falling back to the env vars given by `env`.
+fn parse_args(mut args: impl Iterator<Item = String>, env: impl Fn(&str) -> Option<String>) -> Result<Args, String> {
+    let mut playlist = None;
+    let mut players = Vec::new();
+    let mut clips = None;
+    let defaults = Tools::default();
+    let mut tools = Tools {
+        yt_dlp: env("QUIZ_BOT_YT_DLP").unwrap_or(defaults.yt_dlp),
+        ffmpeg: env("QUIZ_BOT_FFMPEG").unwrap_or(defaults.ffmpeg),
+        ytdl_format: env("QUIZ_BOT_YTDL_FORMAT").unwrap_or(defaults.ytdl_format),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--player" => players.push(args.next().ok_or("--player needs a name")?),
+            "--clips" => clips = Some(args.next().ok_or("--clips needs a directory")?.into()),
+            "--yt-dlp" => tools.yt_dlp = args.next().ok_or("--yt-dlp needs a command")?,
+            "--ffmpeg" => tools.ffmpeg = args.next().ok_or("--ffmpeg needs a command")?,
+            "--ytdl-format" => tools.ytdl_format = args.next().ok_or("--ytdl-format needs a format")?,
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if playlist.is_none() => playlist = Some(arg.into()),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    if players.is_empty() {
+        players.push("player".to_owned());
+    }
+    Ok(Args {
+        playlist: playlist.ok_or("missing playlist")?,
+        players,
+        clips,
+        tools,
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1), |name| env::var(name).ok()) {
+        Ok(args) => args,
+        Err(why) => {
+            eprintln!("{}\n\n{}", why, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let csv = match fs::read_to_string(&args.playlist) {
+        Ok(csv) => csv,
+        Err(why) => {
+            eprintln!("could not read {}: {}", args.playlist.display(), why);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(dir) = &args.clips {
+        if let Err(why) = fs::create_dir_all(dir) {
+            eprintln!("could not create {}: {}", dir.display(), why);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let (rows, failed) = playlist::parse(&csv);
+    if !failed.is_empty() {
+        let failed = failed.iter().map(|index| index.to_string()).collect::<Vec<String>>();
+        eprintln!("총 {}개의 행이 무시되었습니다.\n무시된 행: {}", failed.len(), failed.join(", "));
+    }
+    let mut session = Session::new(Arc::new(SystemClock));
+    for song in songs(rows, args.clips.as_deref(), &args.tools) {
+        session.enqueue(song);
+    }
+
+    match play(&mut session, &args.players, io::stdin().lock(), &mut io::stdout()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(why) => {
+            eprintln!("{}", why);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Songs of `rows`, with their clips written to `clips` if given.
+fn songs(rows: Vec<(usize, Row)>, clips: Option<&Path>, tools: &Tools) -> Vec<Song> {
+    let mut songs = Vec::new();
+    for (index, row) in rows {
+        let Some(dir) = clips else {
+            match &row.keyword {
+                Some(keyword) => songs.push(Song {
+                    title: None,
+                    answer: Some(keyword.clone()),
+                    duration: (row.duration != 0).then_some(row.duration),
+                }),
+                None => eprintln!("{}행은 키워드가 없어 --clips 없이는 정답을 알 수 없습니다.", index),
+            }
+            continue;
+        };
+        match clips::write(&row, index, dir, tools) {
+            Ok(clip) => {
+                println!("{}", clip.path.display());
+                songs.push(Song {
+                    answer: row.keyword.or_else(|| clip.title.clone()),
+                    title: clip.title,
+                    duration: clip.duration,
+                });
+            },
+            Err(why) => eprintln!("{}행을 준비하지 못했습니다: {}", index, why),
+        }
+    }
+    songs
+}
+
+/// Runs the queued songs of `session`, reading guesses from `input` until it ends or `/quit`.
+fn play(
+    session: &mut Session<usize>,
+    players: &[String],
+    input: impl BufRead,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for name in players {
+        session.game.add_player(name.clone());
+    }
+    let mut counter = (0, session.queue_len());
+    session.next_song();
+    print_events(session, &mut counter, out)?;
+
+    let mut lines = input.lines();
+    while session.playing().is_some() {
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+        match line.trim() {
+            "" => continue,
+            "/quit" => break,
+            "/skip" => {
+                session.next_song();
+            },
+            "/score" => print_scores(out, "Score", &session.game)?,
+            line => match guesser(players, line) {
+                Some((player, text)) => {
+                    session.guess(player, &players[player], text);
+                },
+                None => writeln!(out, "<이름>: <정답> 형식으로 입력해 주세요.")?,
+            },
+        }
+        print_events(session, &mut counter, out)?;
+    }
+    if session.playing().is_some() {
+        session.game.finish();
+        print_events(session, &mut counter, out)?;
+    }
+    Ok(())
+}
+
+/// Player and guess of `line`, which is `<name>: <guess>` when there are several players.
+fn guesser<'a>(players: &[String], line: &'a str) -> Option<(usize, &'a str)> {
+    if players.len() == 1 {
+        return Some((0, line));
+    }
+    let (name, text) = line.split_once(':')?;
+    let player = players.iter().position(|player| player == name.trim())?;
+    Some((player, text.trim()))
+}
+
+/// Prints what happened since the last call, `counter` is the number of the song and the total.
+fn print_events(session: &mut Session<usize>, counter: &mut (usize, usize), out: &mut impl Write) -> io::Result<()> {
+    for event in session.game.drain_events() {
+        match event {
+            Event::RoundStarted { .. } => {
+                counter.0 += 1;
+                let duration = session.playing()
+                    .and_then(|song| song.duration)
+                    .map_or(String::new(), |duration| format!(" ({}초)", duration));
+                writeln!(out, "\n[{}/{}] 재생 중{}", counter.0, counter.1, duration)?;
+            },
+            Event::Guessed(guess) => {
+                let verdict = match guess.verdict {
+                    Verdict::Correct => "정답!",
+                    Verdict::Close => "아까워요",
+                    Verdict::Wrong => "틀렸어요",
+                };
+                writeln!(out, "{}: {}", guess.name, verdict)?;
+            },
+            Event::RoundEnded(round) => {
+                let winners = round.log.correct()
+                    .iter()
+                    .map(|(guess, seconds)| format!("{} ({:.1}초)", guess.name, seconds))
+                    .collect::<Vec<String>>();
+                let winners = if winners.is_empty() { "아무도 맞히지 못했습니다.".to_owned() } else { winners.join(", ") };
+                writeln!(out, "\n라운드 결과")?;
+                writeln!(out, "정답: {}", round.answer)?;
+                writeln!(out, "맞힌 사람: {}", winners)?;
+                writeln!(out, "추측: 정답 {} · 근접 {} · 오답 {}",
+                    round.log.count(Verdict::Correct),
+                    round.log.count(Verdict::Close),
+                    round.log.count(Verdict::Wrong))?;
+                print_scores(out, "Score", &session.game)?;
+            },
+            Event::Finished => print_scores(out, "최종 점수", &session.game)?,
+            Event::ScoresChanged => {},
+        }
+    }
+    Ok(())
+}
+
+/// The score embed of the board, `title` over the scores.
+fn print_scores(out: &mut impl Write, title: &str, game: &Game<usize>) -> io::Result<()> {
+    writeln!(out, "\n== {} ==\n{}", title, game.score_text())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()), |_| None)
+    }
+
+    fn run(songs: &[&str], players: &[&str], input: &str) -> String {
+        let mut session = Session::new(Arc::new(SystemClock));
+        for answer in songs {
+            session.enqueue(Song { title: None, answer: Some(answer.to_string()), duration: Some(10) });
+        }
+        let players = players.iter().map(|name| name.to_string()).collect::<Vec<String>>();
+        let mut out = Vec::new();
+        play(&mut session, &players, input.as_bytes(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(args(&["list.csv", "--player", "a", "--player", "b", "--clips", "out"]), Ok(Args {
+            playlist: "list.csv".into(),
+            players: vec!["a".to_owned(), "b".to_owned()],
+            clips: Some("out".into()),
+            tools: Tools::default(),
+        }));
+        assert_eq!(args(&["list.csv"]).unwrap().players, ["player"]);
+        assert!(args(&[]).is_err());
+        assert!(args(&["list.csv", "--loud"]).is_err());
+    }
+
+    #[test]
+    fn tools_come_from_flags_then_env() {
+        let env = |name: &str| (name == "QUIZ_BOT_YT_DLP" || name == "QUIZ_BOT_FFMPEG").then(|| format!("env-{}", name));
+        let args = parse_args(["list.csv", "--ffmpeg", "/opt/ffmpeg"].map(str::to_owned).into_iter(), env).unwrap();
+
+        assert_eq!(args.tools, Tools {
+            yt_dlp: "env-QUIZ_BOT_YT_DLP".to_owned(),
+            ffmpeg: "/opt/ffmpeg".to_owned(),
+            ytdl_format: Tools::default().ytdl_format,
+        });
+    }
+
+    #[test]
+    fn players_take_turns_until_the_queue_runs_out() {
+        let out = run(&["Ditto", "OMG"], &["one", "two"], "one: ditto\ntwo: Ditto\nnobody: OMG\n/skip\n");
+
+        assert!(out.contains("[1/2] 재생 중 (10초)"));
+        assert!(out.contains("one: 아까워요\ntwo: 정답!"));
+        assert!(out.contains("<이름>: <정답> 형식으로 입력해 주세요."));
+        assert!(out.contains("정답: OMG\n맞힌 사람: 아무도 맞히지 못했습니다."));
+        assert!(out.ends_with("== 최종 점수 ==\ntwo: 1\none: 0\n"));
+    }
+
+    #[test]
+    fn quitting_ends_the_game() {
+        let out = run(&["Ditto", "OMG"], &["one"], "Ditto\n/score\n/quit\nOMG\n");
+
+        assert!(out.contains("== Score ==\none: 1"));
+        assert!(out.contains("정답: OMG\n맞힌 사람: 아무도 맞히지 못했습니다."));
+        assert!(out.ends_with("== 최종 점수 ==\none: 1\n"));
+    }
+}
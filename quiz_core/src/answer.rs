@@ -0,0 +1,69 @@
+/// How close a guess was to the answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Correct,
+    /// Differs from the answer only in case, spacing or a few letters.
+    Close,
+    Wrong,
+}
+
+/// Judges `text` against `answer`.
+///
+/// Only an exact match is correct, as before. A guess is close when it matches
+/// ignoring case and spaces, or is off by about a quarter of the letters.
+pub fn judge(answer: &str, text: &str) -> Verdict {
+    if text.trim() == answer {
+        return Verdict::Correct;
+    }
+
+    let normalize = |s: &str| s.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<char>>();
+    let (answer, text) = (normalize(answer), normalize(text));
+    if text.is_empty() {
+        return Verdict::Wrong;
+    }
+    if edit_distance(&answer, &text) <= (answer.len() / 4).max(1) {
+        Verdict::Close
+    } else {
+        Verdict::Wrong
+    }
+}
+
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != cb);
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_answer_is_correct() {
+        assert_eq!(judge("Hype Boy", "Hype Boy"), Verdict::Correct);
+        assert_eq!(judge("Hype Boy", "  Hype Boy\n"), Verdict::Correct);
+    }
+
+    #[test]
+    fn case_spacing_and_typos_are_close() {
+        assert_eq!(judge("Hype Boy", "hypeboy"), Verdict::Close);
+        assert_eq!(judge("Hype Boy", "Hipe Boy"), Verdict::Close);
+        assert_eq!(judge("사건의 지평선", "사건의지평선"), Verdict::Close);
+    }
+
+    #[test]
+    fn anything_else_is_wrong() {
+        assert_eq!(judge("Hype Boy", "Ditto"), Verdict::Wrong);
+        assert_eq!(judge("Hype Boy", "   "), Verdict::Wrong);
+    }
+}
@@ -0,0 +1,63 @@
+//! How a clip is downloaded with yt-dlp and cut with ffmpeg, shared by every frontend.
+//!
+//! Only the arguments are built here, running the tools is left to the frontends.
+
+pub const DEFAULT_YT_DLP: &str = "yt-dlp";
+pub const DEFAULT_FFMPEG: &str = "ffmpeg";
+/// Format passed to `yt-dlp -f` unless configured otherwise.
+pub const DEFAULT_FORMAT: &str = "ba[abr<=128][vcodec=none]/best";
+
+/// Arguments of yt-dlp downloading `url` in `format` to the output `template`,
+/// printing the info of the video as a json line.
+pub fn download_args(url: &str, format: &str, template: &str) -> Vec<String> {
+    ["-j", "--no-simulate", url, "-f", format, "--no-playlist", "-o", template]
+        .map(str::to_owned)
+        .to_vec()
+}
+
+/// Length of the clip from `start` for `duration` seconds of a song `length` long.
+///
+/// A `duration` of `0` plays the rest of the song, whose length may be unknown.
+pub fn length(duration: i64, length: Option<i64>) -> Option<i64> {
+    if duration == 0 { length } else { Some(duration) }
+}
+
+/// Arguments of ffmpeg reading `input`, to be followed by one or more [`output_args`].
+pub fn input_args(input: &str) -> Vec<String> {
+    ["-y", "-i", input].map(str::to_owned).to_vec()
+}
+
+/// Arguments of ffmpeg encoding the clip at `start` and `length` long to `output` in opus.
+///
+/// The whole song is encoded from a `start` of `0` without a `length`,
+/// and without a known `length` the clip goes until the end.
+pub fn output_args(output: &str, start: i64, length: Option<i64>) -> Vec<String> {
+    let mut args = Vec::new();
+    if start != 0 || length.is_some() {
+        args.extend(["-ss".to_owned(), start.to_string()]);
+    }
+    if let Some(length) = length {
+        args.extend(["-t".to_owned(), length.to_string()]);
+    }
+    args.extend(["-c:a".to_owned(), "libopus".to_owned(), output.to_owned()]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clips_are_cut_only_when_asked() {
+        assert_eq!(output_args("full.webm", 0, None), ["-c:a", "libopus", "full.webm"]);
+        assert_eq!(output_args("cut.webm", 30, Some(10)), ["-ss", "30", "-t", "10", "-c:a", "libopus", "cut.webm"]);
+        assert_eq!(output_args("rest.webm", 30, None), ["-ss", "30", "-c:a", "libopus", "rest.webm"]);
+    }
+
+    #[test]
+    fn zero_duration_plays_the_rest() {
+        assert_eq!(length(0, Some(200)), Some(200));
+        assert_eq!(length(0, None), None);
+        assert_eq!(length(10, Some(200)), Some(10));
+    }
+}
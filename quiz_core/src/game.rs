@@ -0,0 +1,328 @@
+use rand::Rng;
+use std::{cmp::Reverse, collections::HashMap, fmt, hash::Hash, sync::Arc};
+
+use crate::{
+    answer::{judge, Verdict},
+    clock::Clock,
+    guess::{Guess, GuessLog},
+    team::{self, Team},
+};
+
+/// What changed in a game, for a frontend to show.
+#[derive(Debug, Clone)]
+pub enum Event<P> {
+    /// Scores or teams changed.
+    ScoresChanged,
+    RoundStarted { answer: String },
+    Guessed(Guess<P>),
+    RoundEnded(Round<P>),
+    /// The session ran out of songs.
+    Finished,
+}
+
+/// Guesses on one song.
+#[derive(Debug, Clone)]
+pub struct Round<P> {
+    pub answer: String,
+    pub log: GuessLog<P>,
+}
+
+/// Scores, teams and the running round of a game, players are identified by `P`.
+///
+/// Scores are kept by display name, teams by player.
+#[derive(Clone)]
+pub struct Game<P> {
+    clock: Arc<dyn Clock>,
+    scores: HashMap<String, i32>,
+    teams: Vec<Team<P>>,
+    // whether players joining later are put in the smallest team
+    auto_balance: bool,
+    round: Option<Round<P>>,
+    events: Vec<Event<P>>,
+}
+
+impl<P: fmt::Debug> fmt::Debug for Game<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Game")
+            .field("scores", &self.scores)
+            .field("teams", &self.teams)
+            .field("auto_balance", &self.auto_balance)
+            .field("round", &self.round)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: Clone + Eq + Hash> Game<P> {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            scores: HashMap::new(),
+            teams: Vec::new(),
+            auto_balance: false,
+            round: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// Takes the events since the last call.
+    pub fn drain_events(&mut self) -> Vec<Event<P>> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn scores_changed(&mut self) {
+        self.events.push(Event::ScoresChanged);
+    }
+
+    pub fn round(&self) -> Option<&Round<P>> {
+        self.round.as_ref()
+    }
+
+    /// Ends the running round and starts one on `answer`, `None` for a song with nothing to guess.
+    ///
+    /// Returns the round which was ended.
+    pub fn start_round(&mut self, answer: Option<String>) -> Option<Round<P>> {
+        let ended = self.end_round();
+        if let Some(answer) = answer {
+            self.events.push(Event::RoundStarted { answer: answer.clone() });
+            self.round = Some(Round { answer, log: GuessLog::start(self.clock.now()) });
+        }
+        ended
+    }
+
+    pub fn end_round(&mut self) -> Option<Round<P>> {
+        let round = self.round.take()?;
+        self.events.push(Event::RoundEnded(round.clone()));
+        Some(round)
+    }
+
+    /// Ends the running round, as there is nothing left to play.
+    pub fn finish(&mut self) {
+        self.end_round();
+        self.events.push(Event::Finished);
+    }
+
+    /// Adds a guess on the running round to its log.
+    pub fn record(&mut self, player: P, name: String, text: String, verdict: Verdict) {
+        let at = self.clock.now();
+        if let Some(round) = &mut self.round {
+            round.log.record(player.clone(), name.clone(), text.clone(), at, verdict);
+            self.events.push(Event::Guessed(Guess { player, name, text, at, verdict }));
+        }
+    }
+
+    /// Judges and records `text` against the running round, scoring a correct guess.
+    ///
+    /// Returns `None` when no round is running.
+    pub fn guess(&mut self, player: P, name: &str, text: &str) -> Option<Verdict> {
+        let verdict = judge(&self.round.as_ref()?.answer, text);
+        self.record(player.clone(), name.to_owned(), text.to_owned(), verdict);
+        if verdict == Verdict::Correct {
+            self.score(player, name);
+        }
+        Some(verdict)
+    }
+
+    /// Adds `name` to the scores, returns `false` if it was there.
+    pub fn add_player(&mut self, name: String) -> bool {
+        if self.scores.contains_key(&name) {
+            return false;
+        }
+        self.scores.insert(name, 0);
+        self.scores_changed();
+        true
+    }
+
+    /// Scores a point for `name`, and for the team of `player` in the team mode.
+    ///
+    /// Only names in the scores can score, returns whether it did.
+    pub fn score(&mut self, player: P, name: &str) -> bool {
+        let Some(value) = self.scores.get_mut(name) else {
+            return false;
+        };
+        *value += 1;
+        if let Some(team) = self.teams.iter_mut().find(|team| team.contains(&player)) {
+            team.add_point(player);
+        }
+        self.scores_changed();
+        true
+    }
+
+    pub fn scores(&self) -> HashMap<String, i32> {
+        self.scores.clone()
+    }
+
+    pub fn restore_scores(&mut self, scores: HashMap<String, i32>) {
+        self.scores = scores;
+        self.scores_changed();
+    }
+
+    pub fn reset_scores(&mut self) {
+        self.scores.clear();
+        self.scores_changed();
+    }
+
+    /// Adds a team, returns `false` if one with the same name exists.
+    pub fn create_team(&mut self, name: String) -> bool {
+        if self.teams.iter().any(|team| team.name == name) {
+            return false;
+        }
+        self.teams.push(Team::new(name));
+        self.scores_changed();
+        true
+    }
+
+    /// Moves `player` to the team named `team_name`, returns `false` if there is no such team.
+    pub fn add_to_team(&mut self, team_name: &str, player: P, name: String) -> bool {
+        if !self.teams.iter().any(|team| team.name == team_name) {
+            return false;
+        }
+        for team in &mut self.teams {
+            if team.name == team_name {
+                team.add_member(player.clone(), name.clone());
+            } else {
+                team.remove_member(&player);
+            }
+        }
+        self.scores_changed();
+        true
+    }
+
+    /// Splits `players` randomly into the teams, making `count` teams first if there are none.
+    ///
+    /// Players joining later are put in the smallest team.
+    pub fn balance_teams(
+        &mut self,
+        count: usize,
+        players: Vec<(P, String)>,
+        team_name: impl Fn(usize) -> String,
+        rng: &mut impl Rng,
+    ) {
+        if self.teams.is_empty() {
+            self.teams = (1..=count).map(|index| Team::new(team_name(index))).collect();
+        }
+        team::balance(&mut self.teams, players, rng);
+        self.auto_balance = true;
+        self.scores_changed();
+    }
+
+    /// Puts a player who joined in the smallest team, when auto-balancing.
+    pub fn join_smallest_team(&mut self, player: P, name: String) {
+        if !self.auto_balance || self.teams.iter().any(|team| team.contains(&player)) {
+            return;
+        }
+        if let Some(team) = self.teams.iter_mut().min_by_key(|team| team.len()) {
+            team.add_member(player, name);
+            self.scores_changed();
+        }
+    }
+
+    pub fn clear_teams(&mut self) {
+        self.teams.clear();
+        self.auto_balance = false;
+        self.scores_changed();
+    }
+
+    pub fn team_names(&self) -> Vec<String> {
+        self.teams.iter().map(|team| team.name.clone()).collect()
+    }
+
+    /// Name of the team of `player`.
+    pub fn team_of(&self, player: &P) -> Option<&str> {
+        self.teams.iter()
+            .find(|team| team.contains(player))
+            .map(|team| team.name.as_str())
+    }
+
+    /// Scores from the highest, preceded by the teams in the team mode.
+    pub fn score_text(&self) -> String {
+        let mut score_vec: Vec<(i32, String)> = self.scores.iter().map(|(k, &v)| (v, k.clone())).collect();
+        score_vec.sort_by_key(|(v, _)| Reverse(*v));
+        let scores = score_vec.iter().map(|(v, k)| format!("{}: {}", k, v)).collect::<Vec<String>>().join("\n");
+        if self.teams.is_empty() {
+            return scores;
+        }
+
+        let mut teams = self.teams.iter().collect::<Vec<&Team<P>>>();
+        teams.sort_by_key(|team| Reverse(team.total()));
+        let teams = teams.iter().map(|team| team.text()).collect::<Vec<String>>().join("\n\n");
+        format!("{}\n\n{}", teams, scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn game() -> (Game<u64>, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()));
+        let mut game = Game::new(clock.clone());
+        game.add_player("one".to_owned());
+        game.add_player("two".to_owned());
+        (game, clock)
+    }
+
+    #[test]
+    fn correct_guess_scores_and_is_timed() {
+        let (mut game, clock) = game();
+        game.start_round(Some("Ditto".to_owned()));
+        clock.advance(Duration::milliseconds(2500));
+
+        assert_eq!(game.guess(1, "one", "ditto"), Some(Verdict::Close));
+        assert_eq!(game.guess(2, "two", "Ditto"), Some(Verdict::Correct));
+        assert_eq!(game.scores()["one"], 0);
+        assert_eq!(game.scores()["two"], 1);
+
+        let round = game.end_round().unwrap();
+        let correct = round.log.correct();
+        assert_eq!(correct.len(), 1);
+        assert_eq!(correct[0].0.name, "two");
+        assert_eq!(correct[0].1, 2.5);
+    }
+
+    #[test]
+    fn no_round_no_guess() {
+        let (mut game, _) = game();
+        assert_eq!(game.guess(1, "one", "anything"), None);
+        game.start_round(None);
+        assert_eq!(game.guess(1, "one", "anything"), None);
+    }
+
+    #[test]
+    fn unknown_names_do_not_score() {
+        let (mut game, _) = game();
+        game.start_round(Some("Ditto".to_owned()));
+        assert_eq!(game.guess(3, "three", "Ditto"), Some(Verdict::Correct));
+        assert!(!game.scores().contains_key("three"));
+    }
+
+    #[test]
+    fn team_points_follow_the_scorer() {
+        let (mut game, _) = game();
+        game.create_team("red".to_owned());
+        game.create_team("blue".to_owned());
+        game.add_to_team("red", 1, "one".to_owned());
+        game.add_to_team("blue", 2, "two".to_owned());
+        game.score(1, "one");
+
+        assert_eq!(game.team_of(&1), Some("red"));
+        assert!(game.score_text().starts_with("**red**: 1\n- one: 1\n\n**blue**: 0"));
+    }
+
+    #[test]
+    fn events_describe_a_round() {
+        let (mut game, _) = game();
+        game.drain_events();
+        game.start_round(Some("Ditto".to_owned()));
+        game.guess(1, "one", "Ditto");
+        game.end_round();
+
+        let events = game.drain_events();
+        assert!(matches!(events[0], Event::RoundStarted { .. }));
+        assert!(matches!(events[1], Event::Guessed(Guess { verdict: Verdict::Correct, .. })));
+        assert!(matches!(events[2], Event::ScoresChanged));
+        assert!(matches!(events[3], Event::RoundEnded(_)));
+        assert!(game.drain_events().is_empty());
+    }
+}
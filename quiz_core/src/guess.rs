@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+
+use crate::answer::Verdict;
+
+#[derive(Debug, Clone)]
+pub struct Guess<P> {
+    pub player: P,
+    pub name: String,
+    pub text: String,
+    pub at: DateTime<Utc>,
+    pub verdict: Verdict,
+}
+
+/// Every guess made while a song was playing.
+#[derive(Debug, Clone)]
+pub struct GuessLog<P> {
+    pub started_at: DateTime<Utc>,
+    pub guesses: Vec<Guess<P>>,
+}
+
+impl<P> GuessLog<P> {
+    /// Starts the log of a song which started playing at `started_at`.
+    pub fn start(started_at: DateTime<Utc>) -> Self {
+        Self {
+            started_at,
+            guesses: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, player: P, name: String, text: String, at: DateTime<Utc>, verdict: Verdict) {
+        self.guesses.push(Guess { player, name, text, at, verdict });
+    }
+
+    /// Correct guesses with the seconds taken since the song started, fastest first.
+    pub fn correct(&self) -> Vec<(&Guess<P>, f64)> {
+        self.guesses.iter()
+            .filter(|guess| guess.verdict == Verdict::Correct)
+            .map(|guess| (guess, (guess.at - self.started_at).num_milliseconds() as f64 / 1000.0))
+            .collect()
+    }
+
+    pub fn count(&self, verdict: Verdict) -> usize {
+        self.guesses.iter().filter(|guess| guess.verdict == verdict).count()
+    }
+}
@@ -0,0 +1,14 @@
+//! Rules of the music quiz, free of Discord.
+//!
+//! Answers are judged, guesses timed against an injected [`clock::Clock`] and scored per
+//! player and team. A [`game::Game`] reports what changed as [`game::Event`]s, which the bot
+//! renders on its board and other frontends print however they like.
+
+pub mod answer;
+pub mod clip;
+pub mod clock;
+pub mod game;
+pub mod guess;
+pub mod playlist;
+pub mod session;
+pub mod team;
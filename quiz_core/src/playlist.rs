@@ -0,0 +1,75 @@
+use regex::Regex;
+
+static YOUTUBE_REGEX: &str = r"^((?:https?:)?//)?((?:www|m)\.)?((?:youtube\.com|youtu.be))(/(?:[\w\-]+\?v=|embed/|v/)?)([\w\-]+)(\S+)?$";
+pub static YOUTUBE_PREFIX: &str = "https://www.youtube.com/watch?v=";
+// group 5 is the video id
+
+/// Video id of a youtube `url`.
+pub fn video_id(url: &str) -> Option<String> {
+    let youtube_pattern = Regex::new(YOUTUBE_REGEX).unwrap();
+    if youtube_pattern.is_match(url) {
+        youtube_pattern.captures(url).unwrap().get(5).map(|m| m.as_str().to_string())
+    } else {
+        None
+    }
+}
+
+/// A line of a playlist csv, `url,start,duration,keyword` of which only the url is required.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub video_id: String,
+    pub start: i64,
+    pub duration: i64,
+    pub keyword: Option<String>,
+}
+
+impl TryFrom<&str> for Row {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut iter = value.split(',');
+        let video_id = iter.next()
+            .ok_or("missing url")
+            .and_then(|url| video_id(url).ok_or("invalid url"))?;
+        let start = iter.next().and_then(|value| value.parse().ok()).unwrap_or(0);
+        let duration = iter.next().and_then(|value| value.parse().ok()).unwrap_or(0);
+        let keyword = iter.next().map(|value| value.to_owned());
+
+        Ok(Row {
+            video_id,
+            start,
+            duration,
+            keyword,
+        })
+    }
+}
+
+/// Rows of `csv` with their line index, and the indices of lines which could not be read.
+pub fn parse(csv: &str) -> (Vec<(usize, Row)>, Vec<usize>) {
+    csv.lines()
+        .enumerate()
+        .fold((Vec::new(), Vec::new()), |(mut rows, mut failed), (index, line)| {
+            match Row::try_from(line) {
+                Ok(row) => rows.push((index, row)),
+                Err(_) => failed.push(index),
+            }
+            (rows, failed)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_take_defaults_and_bad_lines_are_reported() {
+        let csv = "https://www.youtube.com/watch?v=abc_1,30,10,Ditto\nnot a url\nhttps://youtu.be/xyz-2";
+        let (rows, failed) = parse(csv);
+
+        assert_eq!(rows, [
+            (0, Row { video_id: "abc_1".to_owned(), start: 30, duration: 10, keyword: Some("Ditto".to_owned()) }),
+            (2, Row { video_id: "xyz-2".to_owned(), start: 0, duration: 0, keyword: None }),
+        ]);
+        assert_eq!(failed, [1]);
+    }
+}
@@ -0,0 +1,141 @@
+use std::{collections::VecDeque, hash::Hash, sync::Arc};
+
+use crate::{
+    answer::Verdict,
+    clock::Clock,
+    game::Game,
+};
+
+/// A song to play, the interval between songs has no answer.
+#[derive(Debug, Clone, Default)]
+pub struct Song {
+    pub title: Option<String>,
+    pub answer: Option<String>,
+    /// Seconds the clip plays.
+    pub duration: Option<i64>,
+}
+
+/// A queue of songs played one after another, each a round of `game`.
+///
+/// A correct guess ends the song, as it skips the track in the bot.
+#[derive(Debug)]
+pub struct Session<P> {
+    pub game: Game<P>,
+    queue: VecDeque<Song>,
+    playing: Option<Song>,
+}
+
+impl<P: Clone + Eq + Hash> Session<P> {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            game: Game::new(clock),
+            queue: VecDeque::new(),
+            playing: None,
+        }
+    }
+
+    pub fn enqueue(&mut self, song: Song) {
+        self.queue.push_back(song);
+    }
+
+    /// Queues `song` to play right after the playing one.
+    pub fn enqueue_next(&mut self, song: Song) {
+        self.queue.push_front(song);
+    }
+
+    /// Removes the song at `index` of the queue, the playing one is not in it.
+    pub fn remove(&mut self, index: usize) -> Option<Song> {
+        self.queue.remove(index)
+    }
+
+    /// Drops every song and ends the running round, scores and teams stay.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.playing = None;
+        self.game.end_round();
+    }
+
+    /// Songs waiting after the playing one.
+    pub fn queue_len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn playing(&self) -> Option<&Song> {
+        self.playing.as_ref()
+    }
+
+    /// Ends the playing song and plays the next one, `None` when the queue ran out.
+    pub fn next_song(&mut self) -> Option<&Song> {
+        self.playing = self.queue.pop_front();
+        match &self.playing {
+            Some(song) => {
+                self.game.start_round(song.answer.clone());
+            },
+            None => self.game.finish(),
+        }
+        self.playing.as_ref()
+    }
+
+    /// Judges a guess on the playing song, moving to the next song when it is correct.
+    pub fn guess(&mut self, player: P, name: &str, text: &str) -> Option<Verdict> {
+        let verdict = self.game.guess(player, name, text)?;
+        if verdict == Verdict::Correct {
+            self.next_song();
+        }
+        Some(verdict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::SystemClock, game::Event};
+
+    fn song(answer: &str) -> Song {
+        Song { title: None, answer: Some(answer.to_owned()), duration: Some(10) }
+    }
+
+    #[test]
+    fn correct_guess_moves_to_the_next_song() {
+        let mut session = Session::new(Arc::new(SystemClock));
+        session.game.add_player("one".to_owned());
+        session.enqueue(song("Ditto"));
+        session.enqueue(Song::default());
+        session.enqueue(song("OMG"));
+
+        assert_eq!(session.next_song().and_then(|song| song.answer.as_deref()), Some("Ditto"));
+        assert_eq!(session.guess(1, "one", "OMG"), Some(Verdict::Wrong));
+        assert_eq!(session.guess(1, "one", "Ditto"), Some(Verdict::Correct));
+
+        // nothing to guess in the interval
+        assert!(session.playing().unwrap().answer.is_none());
+        assert_eq!(session.guess(1, "one", "OMG"), None);
+
+        session.next_song();
+        assert_eq!(session.guess(1, "one", "OMG"), Some(Verdict::Correct));
+        assert!(session.playing().is_none());
+        assert_eq!(session.game.scores()["one"], 2);
+        assert!(matches!(session.game.drain_events().last(), Some(Event::Finished)));
+    }
+
+    #[test]
+    fn queue_can_be_edited_while_playing() {
+        let mut session = Session::<u64>::new(Arc::new(SystemClock));
+        session.enqueue(song("Ditto"));
+        session.enqueue(song("OMG"));
+        session.enqueue(song("Hype Boy"));
+        session.next_song();
+
+        assert_eq!(session.remove(1).and_then(|song| song.answer).as_deref(), Some("Hype Boy"));
+        session.enqueue_next(song("Attention"));
+        assert_eq!(session.next_song().and_then(|song| song.answer.as_deref()), Some("Attention"));
+        assert_eq!(session.queue_len(), 1);
+
+        session.game.drain_events();
+        session.clear();
+        assert!(session.playing().is_none());
+        assert_eq!(session.queue_len(), 0);
+        assert!(session.game.round().is_none());
+        assert!(matches!(session.game.drain_events()[..], [Event::RoundEnded(_)]));
+    }
+}
@@ -0,0 +1,108 @@
+use rand::{seq::SliceRandom, Rng};
+use std::{cmp::Reverse, collections::HashMap, hash::Hash};
+
+/// A team of the team mode.
+#[derive(Clone, Debug)]
+pub struct Team<P> {
+    pub name: String,
+    members: Vec<(P, String)>,
+    // points scored by each member while in this team
+    points: HashMap<P, i32>,
+}
+
+impl<P: Clone + Eq + Hash> Team<P> {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            members: Vec::new(),
+            points: HashMap::new(),
+        }
+    }
+
+    pub fn contains(&self, player: &P) -> bool {
+        self.members.iter().any(|(id, _)| id == player)
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn add_member(&mut self, player: P, name: String) {
+        if !self.contains(&player) {
+            self.members.push((player, name));
+        }
+    }
+
+    pub fn remove_member(&mut self, player: &P) {
+        self.members.retain(|(id, _)| id != player);
+    }
+
+    pub fn add_point(&mut self, player: P) {
+        *self.points.entry(player).or_default() += 1;
+    }
+
+    pub fn total(&self) -> i32 {
+        self.points.values().sum()
+    }
+
+    /// Team total followed by the contribution of each member.
+    pub fn text(&self) -> String {
+        let mut members = self.members.iter()
+            .map(|(id, name)| (self.points.get(id).copied().unwrap_or(0), name))
+            .collect::<Vec<_>>();
+        members.sort_by_key(|(points, _)| Reverse(*points));
+
+        let mut text = format!("**{}**: {}", self.name, self.total());
+        for (points, name) in members {
+            text.push_str(&format!("\n- {}: {}", name, points));
+        }
+        text
+    }
+}
+
+/// Splits `players` randomly into `teams`, keeping the team sizes within one of each other.
+pub fn balance<P: Clone + Eq + Hash>(teams: &mut [Team<P>], mut players: Vec<(P, String)>, rng: &mut impl Rng) {
+    if teams.is_empty() {
+        return;
+    }
+    for team in teams.iter_mut() {
+        team.members.clear();
+    }
+
+    players.shuffle(rng);
+    for (index, (player, name)) in players.into_iter().enumerate() {
+        let count = teams.len();
+        teams[index % count].add_member(player, name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn balance_keeps_sizes_within_one() {
+        let mut teams = (1..=3).map(|index| Team::new(format!("team {}", index))).collect::<Vec<Team<u64>>>();
+        let players = (0..8).map(|id| (id, format!("player {}", id))).collect();
+        balance(&mut teams, players, &mut StdRng::seed_from_u64(7));
+
+        let sizes = teams.iter().map(Team::len).collect::<Vec<_>>();
+        assert_eq!(sizes.iter().sum::<usize>(), 8);
+        assert!(sizes.iter().max().unwrap() - sizes.iter().min().unwrap() <= 1);
+    }
+
+    #[test]
+    fn points_stay_with_the_team() {
+        let mut team = Team::new("red".to_owned());
+        team.add_member(1u64, "one".to_owned());
+        team.add_point(1);
+        team.remove_member(&1);
+        assert_eq!(team.total(), 1);
+        assert_eq!(team.text(), "**red**: 1");
+    }
+}
@@ -0,0 +1,248 @@
+//! Local HTTP API to inspect and control guild sessions without opening Discord.
+//!
+//! Built with the `admin-api` feature, [`spawn`] serves JSON on `admin_api_addr`.
+//! It refuses to listen anywhere but a loopback address, and asks for `admin_api_token`
+//! as a bearer token when one is set.
+//!
+//! - `GET /guilds`: every guild with a queue
+//! - `GET /guilds/:guild_id`: queue and scores of a guild
+//! - `POST /guilds/:guild_id/skip`: skips the playing track
+//! - `POST /guilds/:guild_id/stop`: ends the session like `/leave`
+//! - `POST /settings/reload`: reloads the guild settings from disk
+
+use poise::serenity_prelude as serenity;
+use serenity::Context;
+
+// also built for the tests, which serve it on sessions of their own
+#[cfg(any(feature = "admin-api", test))]
+#[cfg_attr(not(feature = "admin-api"), allow(dead_code))]
+mod enabled {
+    use axum::{
+        extract::{Path, Request, State},
+        http::{header, StatusCode},
+        middleware::{self, Next},
+        response::{IntoResponse, Response},
+        routing::{get, post},
+        Json, Router,
+    };
+    use poise::serenity_prelude as serenity;
+    use serenity::{async_trait, Context, GuildId};
+
+    use serde::Serialize;
+    use std::{collections::HashMap, sync::Arc};
+    use tracing::{info, warn};
+
+    use crate::{
+        config,
+        connection_handler::{end_session, ConnectionErrorCode},
+        utils::{
+            guild_queue::GuildQueue,
+            guild_settings,
+            youtube_dl::{MetaData, TrackSource},
+        },
+        GuildQueueKey,
+    };
+
+    type ApiResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+    #[derive(Serialize)]
+    struct GuildSummary {
+        guild_id: u64,
+        channel: Option<u64>,
+        host: Option<u64>,
+        queue_len: usize,
+        now_playing: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct QueueEntry {
+        title: Option<String>,
+        keyword: Option<String>,
+        duration: Option<i64>,
+        // `None` for the interval between songs
+        source: Option<TrackSource>,
+    }
+
+    #[derive(Serialize)]
+    struct GuildDetail {
+        #[serde(flatten)]
+        summary: GuildSummary,
+        queue: Vec<QueueEntry>,
+        scores: HashMap<String, i32>,
+    }
+
+    impl From<MetaData> for QueueEntry {
+        fn from(meta: MetaData) -> Self {
+            Self {
+                title: meta.title,
+                keyword: meta.keyword,
+                duration: meta.duration,
+                source: meta.source,
+            }
+        }
+    }
+
+    async fn summary(guild_id: GuildId, guild_queue: &GuildQueue) -> GuildSummary {
+        let queue = guild_queue.current_queue().await;
+        GuildSummary {
+            guild_id: guild_id.get(),
+            channel: guild_queue.channel().await.map(|channel| channel.0.get()),
+            host: guild_queue.host().await.map(|host| host.get()),
+            queue_len: queue.len(),
+            now_playing: queue.into_iter().next().and_then(|(_, meta)| meta.title),
+        }
+    }
+
+    /// The sessions the API controls, the bot's `Context` outside of tests.
+    #[async_trait]
+    pub trait Sessions: Clone + Send + Sync + 'static {
+        /// Every guild with a queue.
+        async fn guild_queues(&self) -> Vec<(GuildId, GuildQueue)>;
+        /// Ends the session of the guild like `/leave`.
+        async fn end_session(&self, guild_id: GuildId) -> Result<(), ConnectionErrorCode>;
+    }
+
+    #[async_trait]
+    impl Sessions for Context {
+        async fn guild_queues(&self) -> Vec<(GuildId, GuildQueue)> {
+            let guild_queue_map = {
+                let data_read = self.data.read().await;
+                data_read.get::<GuildQueueKey>().unwrap().clone()
+            };
+            // DashMap guards must not be held across await
+            guild_queue_map
+                .iter()
+                .map(|entry| (*entry.key(), entry.value().clone()))
+                .collect()
+        }
+
+        async fn end_session(&self, guild_id: GuildId) -> Result<(), ConnectionErrorCode> {
+            end_session(self, guild_id).await
+        }
+    }
+
+    async fn guild_queue<S: Sessions>(sessions: &S, guild_id: u64) -> Result<(GuildId, GuildQueue), (StatusCode, String)> {
+        let guild_id = GuildId::new(guild_id);
+        match sessions.guild_queues().await.into_iter().find(|(id, _)| *id == guild_id) {
+            Some((_, guild_queue)) => Ok((guild_id, guild_queue)),
+            None => Err((StatusCode::NOT_FOUND, format!("no queue for guild {}", guild_id))),
+        }
+    }
+
+    async fn list_guilds<S: Sessions>(State(sessions): State<S>) -> ApiResult<Vec<GuildSummary>> {
+        let guild_queues = sessions.guild_queues().await;
+        let mut guilds = Vec::with_capacity(guild_queues.len());
+        for (guild_id, guild_queue) in guild_queues {
+            guilds.push(summary(guild_id, &guild_queue).await);
+        }
+        guilds.sort_by_key(|guild| guild.guild_id);
+        Ok(Json(guilds))
+    }
+
+    async fn show_guild<S: Sessions>(State(sessions): State<S>, Path(guild_id): Path<u64>) -> ApiResult<GuildDetail> {
+        let (guild_id, guild_queue) = guild_queue(&sessions, guild_id).await?;
+        let queue = guild_queue
+            .current_queue()
+            .await
+            .into_iter()
+            .map(|(_, meta)| QueueEntry::from(meta))
+            .collect();
+        let scores = guild_queue.scores().await;
+        Ok(Json(GuildDetail {
+            summary: summary(guild_id, &guild_queue).await,
+            queue,
+            scores,
+        }))
+    }
+
+    async fn skip<S: Sessions>(State(sessions): State<S>, Path(guild_id): Path<u64>) -> ApiResult<GuildSummary> {
+        let (guild_id, guild_queue) = guild_queue(&sessions, guild_id).await?;
+        guild_queue
+            .skip()
+            .await
+            .map_err(|why| (StatusCode::CONFLICT, format!("{:?}", why)))?;
+        info!("Skipped the track of {} through the admin API", guild_id);
+        Ok(Json(summary(guild_id, &guild_queue).await))
+    }
+
+    async fn stop<S: Sessions>(State(sessions): State<S>, Path(guild_id): Path<u64>) -> ApiResult<()> {
+        let (guild_id, _) = guild_queue(&sessions, guild_id).await?;
+        match sessions.end_session(guild_id).await {
+            // the queue is gone either way
+            Ok(()) | Err(ConnectionErrorCode::NotConnected) => {
+                info!("Ended the session of {} through the admin API", guild_id);
+                Ok(Json(()))
+            },
+            Err(why) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", why))),
+        }
+    }
+
+    async fn authorize(State(token): State<Arc<str>>, request: Request, next: Next) -> Response {
+        let expected = format!("Bearer {}", token);
+        let given = request.headers().get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+        if given != Some(expected.as_str()) {
+            return (StatusCode::UNAUTHORIZED, "missing or wrong bearer token").into_response();
+        }
+        next.run(request).await
+    }
+
+    async fn reload_settings() -> ApiResult<()> {
+        guild_settings::load().await;
+        info!("Reloaded guild settings through the admin API");
+        Ok(Json(()))
+    }
+
+    /// Routes of the API on `sessions`, asking for `token` when given.
+    pub fn router<S: Sessions>(sessions: S, token: Option<&str>) -> Router {
+        let router = Router::new()
+            .route("/guilds", get(list_guilds::<S>))
+            .route("/guilds/:guild_id", get(show_guild::<S>))
+            .route("/guilds/:guild_id/skip", post(skip::<S>))
+            .route("/guilds/:guild_id/stop", post(stop::<S>))
+            .route("/settings/reload", post(reload_settings))
+            .with_state(sessions);
+        match token {
+            Some(token) => router.layer(middleware::from_fn_with_state(Arc::<str>::from(token), authorize)),
+            None => router,
+        }
+    }
+
+    pub async fn serve(ctx: Context) {
+        let config = config::get();
+        // checked to be a loopback address by `config::load`
+        let addr = config.admin_api_addr;
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(why) => {
+                warn!("Failed to bind admin API to {}: {:?}", addr, why);
+                return;
+            }
+        };
+        info!("Serving admin API on http://{}", addr);
+
+        let app = router(ctx, config.admin_api_token.as_deref());
+        if let Err(why) = axum::serve(listener, app).await {
+            warn!("Admin API stopped: {:?}", why);
+        }
+    }
+}
+
+#[cfg(test)]
+pub use enabled::{router, Sessions};
+
+/// Starts the admin API once, later calls do nothing.
+///
+/// Called on `ready` since controlling sessions needs the serenity `Context`.
+pub fn spawn(ctx: &Context) {
+    #[cfg(feature = "admin-api")]
+    {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        static STARTED: AtomicBool = AtomicBool::new(false);
+        if !STARTED.swap(true, Ordering::SeqCst) {
+            tokio::spawn(enabled::serve(ctx.clone()));
+        }
+    }
+    #[cfg(not(feature = "admin-api"))]
+    let _ = ctx;
+}
@@ -1,67 +1,161 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-
 use poise::serenity_prelude as serenity;
-use serenity::{
-    async_trait,
-    GuildId,
-    builder::*,
-    CreateCommand, CommandInteraction, CommandDataOption, EditInteractionResponse, Context,
-    CreateInteractionResponse, CreateEmbed
+use serenity::{Context, EditInteractionResponse, GuildId};
+
+use tracing::{error, warn};
+
+use crate::{
+    command_handler::{ command_return::*, commands::* },
+    connection_handler::ConnectionErrorCode,
+    error::BotError,
+    i18n::{self, Lang},
+    metrics,
+    utils::{guild_queue::{find_guild_queue, GuildQueue}, media_tools, permission::{self, Level}},
 };
 
-use lazy_static::lazy_static;
-use songbird::tracks::PlayMode;
+pub struct Data;
+pub type Error = BotError;
+pub type CommandContext<'a> = poise::ApplicationContext<'a, Data, Error>;
 
-use crate::command_handler::{ command_return::*, commands::* };
+/// Every command the bot provides.
+pub fn commands() -> Vec<poise::Command<Data, Error>> {
+    let mut commands = vec![
+        connect::join(),
+        disconnect::leave(),
+        play::play(),
+        register_channel::regchan(),
+        play_list::playlist(),
+        resume_session::resume_session(),
+        queue::queue(),
+        language::language(),
+        roles::roles(),
+        mode::mode(),
+        team::team(),
+        diagnose::diagnose(),
+    ];
+    i18n::localize_commands(&mut commands);
+    commands
+}
 
-#[async_trait]
-pub trait CommandInterface {
-    async fn run(
-        &self, 
-        ctx: &Context, 
-        command: &CommandInteraction, 
-        options: &[CommandDataOption]
-    ) -> CommandReturn;
-    fn register(&self) -> CreateCommand;
+/// Options of the framework, with every command and the hooks below.
+pub fn framework_options() -> poise::FrameworkOptions<Data, Error> {
+    poise::FrameworkOptions {
+        commands: commands(),
+        pre_command: |ctx| Box::pin(pre_command(ctx)),
+        post_command: |ctx| Box::pin(post_command(ctx)),
+        on_error: |error| Box::pin(on_error(error)),
+        ..Default::default()
+    }
 }
 
-pub struct CommandList {
-    pub commands: HashMap<&'static str, Box<dyn CommandInterface + Send + Sync>>,
+/// Defers every command, as rendering a `CommandReturn` edits the deferred response.
+pub async fn pre_command(ctx: poise::Context<'_, Data, Error>) {
+    if let Err(why) = ctx.defer().await {
+        warn!(command = %ctx.command().qualified_name, error = ?why, "Failed to defer command");
+    }
 }
 
-impl CommandList {
-    pub async fn register(&'static self, gid: GuildId, ctx: &Context) {
-        for (_, command) in &self.commands {
-            if let Err(why) = gid
-                .create_command(&ctx.http, command.register())
-                .await
-            {
-                println!("Cannot create application command: {:#?}", why);
+/// Counts every command which finished without an error.
+pub async fn post_command(ctx: poise::Context<'_, Data, Error>) {
+    metrics::command_executed(&ctx.command().qualified_name, "ok");
+}
+
+/// Replies every failure as an error embed.
+pub async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
+    let (ctx, error) = match error {
+        poise::FrameworkError::Command { error, ctx, .. } => (ctx, error),
+        poise::FrameworkError::ArgumentParse { error, input, ctx, .. } => {
+            (ctx, BotError::Parse(format!("{} ({})", error, input.unwrap_or_default())))
+        },
+        poise::FrameworkError::CommandCheckFailed { error, ctx, .. } => {
+            let error = error.unwrap_or(BotError::Permission("error.check_failed".to_owned()));
+            (ctx, error)
+        },
+        poise::FrameworkError::GuildOnly { ctx, .. } => {
+            (ctx, BotError::Permission("error.guild_only".to_owned()))
+        },
+        error => {
+            if let Err(why) = poise::builtins::on_error(error).await {
+                error!(error = ?why, "Error while handling error");
             }
+            return;
         }
+    };
+
+    metrics::command_executed(&ctx.command().qualified_name, error.kind());
+    warn!(
+        command = %ctx.command().qualified_name,
+        guild = ?ctx.guild_id(),
+        user = %ctx.author().id,
+        kind = error.kind(),
+        error = %error,
+        "Command failed"
+    );
+    let lang = Lang::of(ctx.guild_id());
+    if let Err(why) = ctx.send(poise::CreateReply::default().embed(error.to_embed(lang))).await {
+        error!(error = ?why, "Failed to send error reply");
     }
 }
 
-lazy_static! {
-    pub static ref COMMAND_LIST: CommandList = CommandList {
-        commands: HashMap::from([
-            ("join", connect::command()),
-            ("leave", disconnect::command()),
-            ("play", play::command()),
-            ("regchan", register_channel::command()),
-            ("playlist", play_list::command())
-        ])
-    };
+/// Check for commands which need the user in a voice channel.
+pub async fn in_voice_channel(ctx: poise::Context<'_, Data, Error>) -> Result<bool, Error> {
+    let user_channel = ctx.guild().and_then(|guild| {
+        guild.voice_states
+            .get(&ctx.author().id)
+            .and_then(|voice_state| voice_state.channel_id)
+    });
+    match user_channel {
+        Some(_) => Ok(true),
+        None => Err(ConnectionErrorCode::JoinVoiceChannelFirst.into()),
+    }
 }
 
-pub async fn execute_command(ctx: Arc<serenity::Context>, command: CommandInteraction) {
+/// Check for commands which download songs, failing while a media tool is missing.
+pub async fn media_ready(_ctx: poise::Context<'_, Data, Error>) -> Result<bool, Error> {
+    if media_tools::ready() {
+        Ok(true)
+    } else {
+        Err(BotError::Unavailable("error.media_tools".to_owned()))
+    }
+}
+
+/// Check for commands which add or skip songs.
+pub async fn is_dj(ctx: poise::Context<'_, Data, Error>) -> Result<bool, Error> {
+    has_level(ctx, Level::Dj).await
+}
 
-    command.defer(&ctx.http).await.unwrap();
-    let cmd_result = match COMMAND_LIST.commands.get(command.data.name.as_str()) {
-        Some(result) => result.run(&ctx, &command, &command.data.options).await,
-        None => CommandReturn::String("등록되지않은 명령어입니다.".to_string()),
+/// Check for commands which control the running game.
+pub async fn is_host(ctx: poise::Context<'_, Data, Error>) -> Result<bool, Error> {
+    has_level(ctx, Level::Host).await
+}
+
+async fn has_level(ctx: poise::Context<'_, Data, Error>, level: Level) -> Result<bool, Error> {
+    let (Some(guild_id), Some(member)) = (ctx.guild_id(), ctx.author_member().await) else {
+        return Ok(false);
+    };
+    // without a session nobody is the host
+    let host = match find_guild_queue(ctx.serenity_context(), guild_id).await {
+        Some(guild_queue) => guild_queue.host().await,
+        None => None,
     };
+    if permission::allows(guild_id, &member, host, level) {
+        Ok(true)
+    } else {
+        Err(BotError::Permission(level.denied_key().to_owned()))
+    }
+}
+
+/// Session of the guild for commands which act on a running game.
+///
+/// Only the commands starting a game create a session, the others fail when none is running.
+pub async fn running_session(ctx: &Context, guild_id: GuildId) -> Result<GuildQueue, BotError> {
+    find_guild_queue(ctx, guild_id).await
+        .ok_or_else(|| BotError::Unavailable("error.no_session".to_owned()))
+}
+
+/// Renders the result of a command into the deferred response.
+pub async fn send_return(ctx: CommandContext<'_>, cmd_result: CommandReturn) -> Result<(), Error> {
+    let command = ctx.interaction;
+    let ctx = ctx.serenity_context();
 
     match cmd_result {
         CommandReturn::String(content) => {
@@ -72,77 +166,21 @@ pub async fn execute_command(ctx: Arc<serenity::Context>, command: CommandIntera
                 })
                 .await
             {
-                println!(
-                    "Failed to send Single-string \"{:?}\" from command \"{}\".",
-                    content, command.data.name
-                );
-                println!("{:#?}", why);
+                warn!(command = %command.data.name, content = %content, error = ?why, "Failed to send single-string");
             }
         }
         CommandReturn::SingleEmbed(embed) => {
             if let Err(why) = command
                 .edit_response(&ctx.http, {
                     EditInteractionResponse::new()
-                        .add_embed(embed.clone())
+                        .add_embed(*embed.clone())
                 })
                 .await
             {
-                println!(
-                    "Failed to send single-embed \"{:#?}\" from command \"{}\".",
-                    embed, command.data.name
-                );
-                println!("{:#?}", why);
+                warn!(command = %command.data.name, embed = ?embed, error = ?why, "Failed to send single-embed");
             }
         }
-        CommandReturn::SongInfoEmbed(handle, meta) => {
-            if let Err(why) = command
-                .edit_response(&ctx.http, {
-                    EditInteractionResponse::new()
-                        .content("재생 시작")
-                })
-                .await
-            {
-                println!(
-                    "Failed to send Single-string from command \"{}\".",
-                    command.data.name
-                );
-                println!("{:#?}", why);
-            };
-            let title = meta.title.clone().unwrap_or("제목 없음".to_owned());
-            let duration = meta.duration.clone().unwrap_or(0);
-            let info = handle.get_info().await.unwrap();
-            let embed = CreateEmbed::new()
-                .title(&title)
-                .description(format!("{}:{} / {}:{}", 
-                    info.play_time.as_secs() / 60, info.play_time.as_secs() % 60,
-                    duration / 60, duration % 60));
-            let mut msg = command.channel_id.send_message(
-                &ctx.http,
-                CreateMessage::new().embed(embed.clone()))
-                .await
-                .unwrap();
-
-            tokio::spawn(async move {
-                while let Ok(info) = handle.get_info().await {
-                    if info.playing == PlayMode::Stop {
-                        println!("{} stopped", &title);
-                        break;
-                    }
-                    let embed = CreateEmbed::new()
-                        .title(title.as_str())
-                        .description(format!("{}:{} / {}:{}", 
-                            info.play_time.as_secs() / 60, info.play_time.as_secs() % 60,
-                            duration / 60, duration % 60));
-                    if let Err(e) = msg.edit(&ctx.http, EditMessage::new().embed(embed)).await {
-                        println!("Failed to update message: {:?}", e);
-                    }
-                    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                } 
-            });
-            
-            
-        }
-        _ => command.delete_response(&ctx.http).await.unwrap(),
     }
 
-}
\ No newline at end of file
+    Ok(())
+}
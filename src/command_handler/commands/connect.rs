@@ -1,40 +1,11 @@
-use poise::serenity_prelude as serenity;
-use serenity::{
-    async_trait, CreateCommand, Context, CommandInteraction, CommandDataOption, VoiceState,
-    UserId
-};
-
 use crate::{command_handler::{
         command_handler::*,
         command_return::CommandReturn,
-    }, connection_handler::establish_connection};
-
-use std::collections::HashMap;
-
-struct Connect;
-
-pub fn command() -> Box<dyn CommandInterface + Sync + Send> {
-    Box::new(Connect)
-}
-
-#[async_trait]
-impl CommandInterface for Connect {
-    async fn run(
-        &self, 
-        ctx: &Context, 
-        command: &CommandInteraction, 
-        _options: &[CommandDataOption]
-    ) -> CommandReturn {
-        match establish_connection(ctx, command).await {
-            Ok(res) => CommandReturn::String("접속".to_owned()),
-            Err(why) => CommandReturn::String(format!("{:?}", why))
-        }
-    }
+    }, connection_handler::establish_connection, i18n::{t, Lang}};
 
-    fn register(&self) -> CreateCommand {
-        let command = CreateCommand::new("");
-        command
-            .name("join")
-            .description("Join the voice channel")
-    }
+/// Join the voice channel
+#[poise::command(slash_command, guild_only, check = "in_voice_channel")]
+pub async fn join(ctx: CommandContext<'_>) -> Result<(), Error> {
+    establish_connection(ctx.serenity_context(), ctx.interaction).await?;
+    send_return(ctx, CommandReturn::String(t(Lang::of(ctx.guild_id()), "reply.connected"))).await
 }
\ No newline at end of file
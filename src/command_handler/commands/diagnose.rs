@@ -0,0 +1,61 @@
+use poise::serenity_prelude as serenity;
+use serenity::{Colour, CreateEmbed};
+
+use crate::{
+    command_handler::{
+        command_handler::*,
+        command_return::CommandReturn,
+    },
+    i18n::{t, tr, Lang},
+    utils::{media_tools, youtube_dl::cache_usage},
+};
+
+/// report media tools, cache, voice connection and latency
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD")]
+pub async fn diagnose(ctx: CommandContext<'_>) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    let lang = Lang::of(Some(guild_id));
+
+    // probed again, so that a tool installed after startup enables the commands
+    let report = media_tools::probe().await;
+    let mut tools = report.tools.iter()
+        .map(|tool| match &tool.version {
+            Ok(version) => format!("✅ {} `{}`", tool.name, version),
+            Err(why) => format!("❌ {} (`{}`): {}", tool.name, tool.command, why),
+        })
+        .collect::<Vec<String>>();
+    tools.push(format!("{} libopus", if report.libopus { "✅" } else { "❌" }));
+
+    let (files, bytes) = cache_usage().await;
+    let cache = tr(lang, "diagnose.cache_value", &[
+        ("count", files.to_string()),
+        ("size", format!("{:.1}", bytes as f64 / 1024.0 / 1024.0)),
+    ]);
+
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    let channel = match manager.get(guild_id) {
+        Some(call) => call.lock().await.current_channel(),
+        None => None,
+    };
+    let voice = match channel {
+        Some(channel) => tr(lang, "diagnose.voice_connected", &[("channel", format!("<#{}>", channel.0))]),
+        None => t(lang, "diagnose.voice_disconnected"),
+    };
+
+    let latency = poise::Context::Application(ctx).ping().await;
+    // zero until the shard has sent a heartbeat
+    let latency = if latency.is_zero() {
+        t(lang, "diagnose.latency_unknown")
+    } else {
+        tr(lang, "diagnose.latency_value", &[("ms", latency.as_millis().to_string())])
+    };
+
+    let embed = CreateEmbed::new()
+        .title(t(lang, "diagnose.title"))
+        .colour(if report.is_ready() { Colour::DARK_GREEN } else { Colour::RED })
+        .field(t(lang, "diagnose.tools"), tools.join("\n"), false)
+        .field(t(lang, "diagnose.cache"), cache, false)
+        .field(t(lang, "diagnose.voice"), voice, true)
+        .field(t(lang, "diagnose.latency"), latency, true);
+    send_return(ctx, CommandReturn::SingleEmbed(Box::new(embed))).await
+}
@@ -1,35 +1,12 @@
-use poise::serenity_prelude as serenity;
-use serenity::{
-    async_trait, CreateCommand, Context, CommandInteraction, CommandDataOption
-};
-
 use crate::{command_handler::{
         command_handler::*,
         command_return::CommandReturn,
-    }, connection_handler::terminate_connection};
-
-struct DisConnect;
-
-pub fn command() -> Box<dyn CommandInterface + Sync + Send> {
-    Box::new(DisConnect)
-}
-
-#[async_trait]
-impl CommandInterface for DisConnect {
-    async fn run(
-        &self, 
-        ctx: &Context, 
-        command: &CommandInteraction, 
-        _options: &[CommandDataOption]
-    ) -> CommandReturn {
-        terminate_connection(ctx, command).await;
-        CommandReturn::String("접속 종료".to_owned())
-    }
-
-    fn register(&self) -> CreateCommand {
-        let command = CreateCommand::new("");
-        command
-            .name("leave")
-            .description("leave the voice channel")
-    }
+    }, connection_handler::end_session, i18n::{t, Lang}};
+
+/// leave the voice channel
+#[poise::command(slash_command, guild_only, check = "is_host")]
+pub async fn leave(ctx: CommandContext<'_>) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    end_session(ctx.serenity_context(), guild_id).await?;
+    send_return(ctx, CommandReturn::String(t(Lang::of(Some(guild_id)), "reply.disconnected"))).await
 }
\ No newline at end of file
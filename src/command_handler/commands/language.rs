@@ -0,0 +1,20 @@
+use crate::{
+    command_handler::{
+        command_handler::*,
+        command_return::CommandReturn,
+    },
+    i18n::{t, Lang},
+    utils::guild_settings,
+};
+
+/// Set the language of the bot
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD")]
+pub async fn language(
+    ctx: CommandContext<'_>,
+    #[description = "language to use"] language: Lang,
+) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    guild_settings::update(guild_id, |settings| settings.language = language).await;
+
+    send_return(ctx, CommandReturn::String(t(language, "language.changed"))).await
+}
@@ -1,6 +1,12 @@
-pub mod launch;
 pub mod connect;
 pub mod disconnect;
 pub mod play;
 pub mod register_channel;
-pub mod play_list;
\ No newline at end of file
+pub mod play_list;
+pub mod resume_session;
+pub mod queue;
+pub mod language;
+pub mod roles;
+pub mod mode;
+pub mod team;
+pub mod diagnose;
\ No newline at end of file
@@ -0,0 +1,24 @@
+use poise::ChoiceParameter;
+
+use crate::{
+    command_handler::{
+        command_handler::*,
+        command_return::CommandReturn,
+    },
+    i18n::{tr, Lang},
+    utils::guild_settings::{self, AnswerMode},
+};
+
+/// Set how players answer
+#[poise::command(slash_command, guild_only, check = "is_host")]
+pub async fn mode(
+    ctx: CommandContext<'_>,
+    #[description = "answer mode"] mode: AnswerMode,
+) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    guild_settings::update(guild_id, |settings| settings.answer_mode = mode).await;
+
+    let lang = Lang::of(Some(guild_id));
+    let name = mode.localized_name(lang.locale()).unwrap_or(mode.name()).to_owned();
+    send_return(ctx, CommandReturn::String(tr(lang, "mode.changed", &[("mode", name)]))).await
+}
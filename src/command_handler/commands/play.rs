@@ -1,139 +1,55 @@
-use lazy_static::lazy_static;
-use poise::serenity_prelude as serenity;
-use serenity::{
-    async_trait, CreateCommand, Context, CommandInteraction, CommandDataOption, 
-    CreateCommandOption, CommandOptionType, GuildId, CreateEmbed
-};
-use songbird::{
-    input::{File, Input},
-    tracks::TrackQueue,
-};
-
 use crate::{
     command_handler::{
         command_handler::*,
         command_return::CommandReturn,
-    }, connection_handler::*, global::*, utils::{guild_queue::get_guild_queue, url_checker::url_checker, youtube_dl::{ytdl_optioned, MetaData}}
+    }, connection_handler::*, error::BotError, i18n::{t, Lang}, utils::{guild_queue::get_guild_queue, url_checker::url_checker, youtube_dl::{prepare_source, TrackSource}}
 };
 
-use std::{collections::HashMap, io::Read, path::PathBuf, sync::Arc};
-
-use chrono::Utc;
-
-struct Play;
-
-pub fn command() -> Box<dyn CommandInterface + Sync + Send> {
-    Box::new(Play)
+/// Play a youtube video
+#[poise::command(slash_command, guild_only, check = "in_voice_channel", check = "is_dj", check = "media_ready")]
+pub async fn play(
+    ctx: CommandContext<'_>,
+    #[description = "youtube url"] url: String,
+    #[description = "start from"] start: Option<i64>,
+    #[description = "play how long"] duration: Option<i64>,
+    #[description = "keyword for skip the song"] skip: Option<String>,
+) -> Result<(), Error> {
+    let result = run(ctx, url, start, duration, skip).await?;
+    send_return(ctx, result).await
 }
 
-#[async_trait]
-impl CommandInterface for Play {
-    async fn run(
-        &self, 
-        ctx: &Context, 
-        command: &CommandInteraction, 
-        options: &[CommandDataOption]
-    ) -> CommandReturn {
-
-        match establish_connection(ctx, command).await {
-            Ok(_) => (),
-            Err(why) => match why {
-                ConnectionErrorCode::JoinVoiceChannelFirst => return CommandReturn::String("음성채널에 먼저 접속해주세요.".to_owned()),
-                ConnectionErrorCode::AlreadyInUse => return CommandReturn::String("다른 채널에서 사용중입니다.".to_owned()),
-                _ => return CommandReturn::String("연결에 실패했습니다.".to_owned()),
-            },
-        };
-
-        let url = options.get(0)
-            .and_then(|option| option.value.as_str())
-            .and_then(url_checker)
-            .unwrap();
-
-        let mut start = 0;
-        let mut duration = 0;
-        let mut skip = None;
-
-        for option in &options[1..] {
-            match option.name.as_str() {
-                "start" => {
-                    start = match option.value.as_i64() {
-                        Some(value) if value > 0 => value,
-                        _ => 0,
-                    }
-                },
-                "duration" => {
-                    duration = match option.value.as_i64() {
-                        Some(value) if value > 0 => value,
-                        _ => 0,
-                    }
-                },
-                "skip" => {
-                    skip = match option.value.as_str() {
-                        Some(value) => Some(value.to_owned()),
-                        _ => None,
-                    }
-                },
-                _ => {}
-            }
-        }
-
-        let guild_id = command.guild_id.unwrap();
-        let guild_queue = get_guild_queue(ctx, guild_id).await;
-
-        let manager = songbird::get(ctx).await.unwrap().clone();
-        if let Some(handler_lock) = manager.get(guild_id) {
-            let (path, output) = ytdl_optioned(&url, start, duration).await.unwrap();
-            let src = File::new(path);
-            let mut handler = handler_lock.lock().await;
-            let mut meta: MetaData = output.into();
-            meta.keyword = skip;
-            
-            // guild_queue.add_source((INTERVAL.as_ref() as &[u8]).into(), INTERVAL_META.clone(), &mut handler).await;
-            guild_queue.add_source(src.into(), meta, &mut handler, true).await;
-            
-            CommandReturn::String("큐에 추가됨".to_owned())
-        } else {
-            CommandReturn::String("재생 실패".to_owned())
-        }
-
-    }
-
-    fn register(&self) -> CreateCommand {
-        let command = CreateCommand::new("");
-        command
-            .name("play")
-            .description("Join the voice channel")
-            .add_option({
-                let option = CreateCommandOption::new(
-                    CommandOptionType::String,
-                    "url",
-                    "youtube url"
-                );
-                option.required(true)
-            })
-            .add_option({
-                let option = CreateCommandOption::new(
-                    CommandOptionType::Integer,
-                    "start",
-                    "start from"
-                );
-                option
-            })
-            .add_option({
-                let option = CreateCommandOption::new(
-                    CommandOptionType::Integer,
-                    "duration",
-                    "play how long"
-                );
-                option
-            })
-            .add_option({
-                let option = CreateCommandOption::new(
-                    CommandOptionType::String,
-                    "skip",
-                    "keyword for skip the song"
-                );
-                option
-            })
+async fn run(
+    ctx: CommandContext<'_>,
+    url: String,
+    start: Option<i64>,
+    duration: Option<i64>,
+    skip: Option<String>,
+) -> Result<CommandReturn, BotError> {
+    let (serenity_ctx, command) = (ctx.serenity_context(), ctx.interaction);
+
+    establish_connection(serenity_ctx, command).await?;
+
+    let url = url_checker(&url)
+        .ok_or_else(|| BotError::Parse("play.not_youtube".to_owned()))?;
+    let start = start.filter(|&value| value > 0).unwrap_or(0);
+    let duration = duration.filter(|&value| value > 0).unwrap_or(0);
+
+    let guild_id = command.guild_id.unwrap();
+    let guild_queue = get_guild_queue(serenity_ctx, guild_id).await;
+    guild_queue.claim_host(command.user.id);
+    guild_queue.register_channel_if_empty(command.channel_id).await;
+
+    let manager = songbird::get(serenity_ctx).await.unwrap().clone();
+    if let Some(handler_lock) = manager.get(guild_id) {
+        let source = TrackSource { url, start, duration, keyword: skip };
+        let (src, meta) = prepare_source(&source).await?;
+        let mut handler = handler_lock.lock().await;
+        
+        // guild_queue.add_source((INTERVAL.as_ref() as &[u8]).into(), INTERVAL_META.clone(), &mut handler).await;
+        guild_queue.add_source(src, meta, &mut handler, true).await?;
+        
+        Ok(CommandReturn::String(t(Lang::of(Some(guild_id)), "play.queued")))
+    } else {
+        Err(ConnectionErrorCode::NotConnected.into())
     }
 }
\ No newline at end of file
@@ -1,147 +1,124 @@
-use lazy_static::lazy_static;
 use poise::serenity_prelude as serenity;
-use serenity::{
-    async_trait, CreateCommand, Context, CommandInteraction, CommandDataOption, 
-    CreateCommandOption, CommandOptionType, GuildId, CreateEmbed
-};
-use songbird::{
-    input::{File, Input},
-    tracks::TrackQueue,
-};
-use tracing::info;
+use quiz_core::playlist;
+use tracing::{info, warn};
 
 use crate::{
     command_handler::{
         command_handler::*,
         command_return::CommandReturn,
-    }, connection_handler::*, global::*, utils::{guild_queue::get_guild_queue, url_checker::{self, url_checker}, youtube_dl::{ytdl_optioned, MetaData}}
+    }, connection_handler::*, error::BotError, i18n::{tr, Lang}, utils::{guild_queue::get_guild_queue, quiz_store, youtube_dl::{prepare_source, TrackSource}}
 };
 
-use std::{collections::HashMap, io::Read, path::PathBuf, sync::Arc};
-
-use chrono::Utc;
-
-struct PlayList;
-
-pub fn command() -> Box<dyn CommandInterface + Sync + Send> {
-    Box::new(PlayList)
+/// play youtube from csv file or a quiz set of the dashboard
+#[poise::command(slash_command, guild_only, check = "in_voice_channel", check = "is_dj", check = "media_ready")]
+pub async fn playlist(
+    ctx: CommandContext<'_>,
+    #[description = "csv file"] csv: Option<serenity::Attachment>,
+    #[description = "quiz set"]
+    #[autocomplete = "autocomplete_set"]
+    set: Option<String>,
+) -> Result<(), Error> {
+    let result = run(ctx, csv, set).await?;
+    send_return(ctx, result).await
 }
 
-#[derive(Debug)]
-struct ListOption {
-    pub url: String,
-    pub start: i64,
-    pub duration: i64,
-    pub skip: Option<String>,
+async fn autocomplete_set(_ctx: CommandContext<'_>, partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    quiz_store::load_all().await
+        .into_iter()
+        .filter(|set| set.name.to_lowercase().contains(&partial.to_lowercase()))
+        .take(25)
+        .map(|set| serenity::AutocompleteChoice::new(set.name, set.id.to_string()))
+        .collect()
 }
 
-impl TryFrom<&str> for ListOption {
-    type Error = &'static str;
-    
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut iter = value.split(',');
-        let url = iter.next()
-            .ok_or("missing url")
-            .and_then(|url| url_checker(url).ok_or("invalid url"))
-            .unwrap();
-        let start = iter.next().and_then(|value| value.parse().ok()).unwrap_or(0);
-        let duration = iter.next().and_then(|value| value.parse().ok()).unwrap_or(0);
-        let skip = iter.next().map(|value| value.to_owned());
-
-        Ok(ListOption {
-            url,
-            start,
-            duration,
-            skip,
-        })
+/// Rows to queue with their index, and the indices of rows which could not be read.
+async fn read_rows(
+    csv: Option<serenity::Attachment>,
+    set: Option<String>,
+) -> Result<(Vec<(usize, TrackSource)>, Vec<usize>), BotError> {
+    if let Some(set) = set {
+        let set = match set.parse() {
+            Ok(id) => quiz_store::load(id).await,
+            Err(_) => None,
+        }
+        .ok_or(BotError::Parse("playlist.set_not_found".to_owned()))?;
+        return Ok((set.entries.iter().map(|entry| entry.source()).enumerate().collect(), Vec::new()));
     }
-}
-
-#[async_trait]
-impl CommandInterface for PlayList {
-    async fn run(
-        &self, 
-        ctx: &Context, 
-        command: &CommandInteraction, 
-        options: &[CommandDataOption]
-    ) -> CommandReturn {
 
-        match establish_connection(ctx, command).await {
-            Ok(_) => (),
-            Err(why) => match why {
-                ConnectionErrorCode::JoinVoiceChannelFirst => return CommandReturn::String("음성채널에 먼저 접속해주세요.".to_owned()),
-                ConnectionErrorCode::AlreadyInUse => return CommandReturn::String("다른 채널에서 사용중입니다.".to_owned()),
-                _ => return CommandReturn::String("연결에 실패했습니다.".to_owned()),
-            },
-        };
-
-        let attachment_id = options.get(0)
-            .and_then(|option| option.value.as_attachment_id())
-            .unwrap();
+    let csv = csv.ok_or(BotError::Parse("playlist.missing_source".to_owned()))?;
+    let stream = csv.download().await?;
+    let csv = String::from_utf8(stream)
+        .map_err(|_| BotError::Parse("playlist.not_utf8".to_owned()))?;
+    let (rows, failed) = playlist::parse(&csv);
+    let rows = rows.into_iter()
+        .map(|(index, row)| (index, TrackSource {
+            url: row.video_id,
+            start: row.start,
+            duration: row.duration,
+            keyword: row.keyword,
+        }))
+        .collect();
+    Ok((rows, failed))
+}
 
-        let attachment = command.data.resolved.attachments.get(&attachment_id).unwrap();
-        let stream = attachment.download().await.unwrap();
-        let (lines, failed) = String::from_utf8(stream)
-            .unwrap()
-            .lines()
-            .enumerate()
-            .fold((Vec::new(), Vec::new()), |(mut lines, mut failed), (index, line)| {
-                match ListOption::try_from(line) {
-                    Ok(option) => lines.push(option),
-                    Err(_) => failed.push(index),
+async fn run(
+    ctx: CommandContext<'_>,
+    csv: Option<serenity::Attachment>,
+    set: Option<String>,
+) -> Result<CommandReturn, BotError> {
+    let (ctx, command) = (ctx.serenity_context(), ctx.interaction);
+
+    let (lines, mut failed) = read_rows(csv, set).await?;
+    establish_connection(ctx, command).await?;
+
+    let guild_id = command.guild_id.unwrap();
+    let lang = Lang::of(Some(guild_id));
+    let guild_queue = get_guild_queue(ctx, guild_id).await;
+    guild_queue.claim_host(command.user.id);
+    guild_queue.register_channel_if_empty(command.channel_id).await;
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    
+    info!("{:?}", lines);
+
+    let mut added = 0;
+    for (index, source) in &lines {
+        let manager_clone = manager.clone();
+        if let Some(handler_lock) = manager_clone.get(guild_id) {
+            let (src, meta) = match prepare_source(source).await {
+                Ok(prepared) => prepared,
+                Err(why) => {
+                    warn!(url = %source.url, error = ?why, "Failed to prepare playlist entry");
+                    failed.push(*index);
+                    continue;
                 }
-                (lines, failed)
-            });
-
-        let guild_id = command.guild_id.unwrap();
-        let guild_queue = get_guild_queue(ctx, guild_id).await;
-        let manager = songbird::get(ctx).await.unwrap().clone();
-        
-        info!("{:?}", lines);
-
-        for line in &lines {
-            let manager_clone = manager.clone();
-            if let Some(handler_lock) = manager_clone.get(guild_id) {
-                let (path, output) = ytdl_optioned(&line.url, line.start, line.duration).await.unwrap();
-                let src = File::new(path);
-                let mut handler = handler_lock.lock().await;
-                let mut meta: MetaData = output.into();
-                meta.keyword = line.skip.clone();
-                
-                // guild_queue.add_source((INTERVAL.as_ref() as &[u8]).into(), INTERVAL_META.clone(), &mut handler).await;
-                guild_queue.add_source(src.into(), meta, &mut handler, true).await;
+            };
+            let mut handler = handler_lock.lock().await;
+            
+            // guild_queue.add_source((INTERVAL.as_ref() as &[u8]).into(), INTERVAL_META.clone(), &mut handler).await;
+            if let Err(why) = guild_queue.add_source(src, meta, &mut handler, true).await {
+                warn!(url = %source.url, error = ?why, "Failed to queue playlist entry");
+                failed.push(*index);
+                continue;
             }
+            added += 1;
         }
+    }
+    failed.sort();
 
-        let mut result = String::new();
-        if !failed.is_empty() {
-            result.push_str(
-                format!("총 {}개의 행이 무시되었습니다.\n무시된 행: {}\n",
-                    failed.len(),
-                    failed.iter()
-                        .map(|index| index.to_string())
-                        .collect::<Vec<String>>()
-                        .join(", "))
-                .as_str());
-        }
+    let mut result = String::new();
+    if !failed.is_empty() {
         result.push_str(
-            format!("총 {}개의 곡이 추가되었습니다.", lines.len())
+            tr(lang, "playlist.ignored", &[
+                ("count", failed.len().to_string()),
+                ("rows", failed.iter()
+                    .map(|index| index.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")),
+            ])
             .as_str());
-        CommandReturn::String(result)
-    }
-
-    fn register(&self) -> CreateCommand {
-        let command = CreateCommand::new("");
-        command
-            .name("playlist")
-            .description("play youtube from csv file")
-            .add_option({
-                let option = CreateCommandOption::new(
-                    CommandOptionType::Attachment,
-                    "csv",
-                    "csv file"
-                );
-                option.required(true)
-            })
     }
+    result.push_str(
+        tr(lang, "playlist.added", &[("count", added.to_string())])
+        .as_str());
+    Ok(CommandReturn::String(result))
 }
\ No newline at end of file
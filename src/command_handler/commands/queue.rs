@@ -0,0 +1,85 @@
+use poise::serenity_prelude as serenity;
+use serenity::{AutocompleteChoice, CreateEmbed};
+
+use crate::{
+    command_handler::{
+        command_handler::*,
+        command_return::CommandReturn,
+    },
+    error::BotError,
+    i18n::{t, tr, Lang},
+    utils::guild_queue::find_guild_queue
+};
+
+/// manage the play queue
+#[poise::command(slash_command, guild_only, subcommands("list", "skip", "remove"), subcommand_required)]
+pub async fn queue(_ctx: CommandContext<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// show queued songs
+#[poise::command(slash_command, guild_only)]
+pub async fn list(ctx: CommandContext<'_>) -> Result<(), Error> {
+    let lang = Lang::of(ctx.guild_id());
+    let guild_queue = running_session(ctx.serenity_context(), ctx.interaction.guild_id.unwrap()).await?;
+    let titles = guild_queue.current_queue().await
+        .into_iter()
+        .filter(|(_, meta)| meta.source.is_some())
+        .enumerate()
+        .map(|(index, (_, meta))| format!("{}. {}", index + 1, meta.title.unwrap_or(t(lang, "common.untitled"))))
+        .collect::<Vec<String>>();
+
+    let description = if titles.is_empty() { t(lang, "common.none") } else { titles.join("\n") };
+    let embed = CreateEmbed::new().title(t(lang, "queue.title")).description(description);
+    send_return(ctx, CommandReturn::SingleEmbed(Box::new(embed))).await
+}
+
+/// skip the current song
+#[poise::command(slash_command, guild_only, check = "is_dj")]
+pub async fn skip(ctx: CommandContext<'_>) -> Result<(), Error> {
+    let guild_queue = running_session(ctx.serenity_context(), ctx.interaction.guild_id.unwrap()).await?;
+    guild_queue.skip().await?;
+    send_return(ctx, CommandReturn::String(t(Lang::of(ctx.guild_id()), "queue.skipped"))).await
+}
+
+async fn autocomplete_queued(ctx: CommandContext<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    let Some(guild_id) = ctx.interaction.guild_id else {
+        return Vec::new();
+    };
+    let Some(guild_queue) = find_guild_queue(ctx.serenity_context(), guild_id).await else {
+        return Vec::new();
+    };
+    guild_queue
+        .current_queue()
+        .await
+        .into_iter()
+        .enumerate()
+        // the head is playing now, use skip instead
+        .skip(1)
+        .filter(|(_, (_, meta))| meta.source.is_some())
+        .filter_map(|(index, (_, meta))| meta.title.map(|title| (index, title)))
+        .filter(|(_, title)| title.to_lowercase().contains(&partial.to_lowercase()))
+        .map(|(index, title)| AutocompleteChoice::new(title, index as i64))
+        .collect()
+}
+
+/// remove a song from the queue
+#[poise::command(slash_command, guild_only, check = "is_dj")]
+pub async fn remove(
+    ctx: CommandContext<'_>,
+    #[description = "song to remove"]
+    #[autocomplete = "autocomplete_queued"]
+    track: i64,
+) -> Result<(), Error> {
+    let guild_queue = running_session(ctx.serenity_context(), ctx.interaction.guild_id.unwrap()).await?;
+    let index = usize::try_from(track).ok().filter(|&index| index > 0);
+
+    let removed = match index {
+        Some(index) => guild_queue.remove_song(index).await,
+        None => None,
+    };
+    let meta = removed.ok_or_else(|| BotError::Parse("queue.not_found".to_owned()))?;
+    let lang = Lang::of(ctx.guild_id());
+    let title = meta.title.unwrap_or(t(lang, "common.untitled"));
+    send_return(ctx, CommandReturn::String(tr(lang, "queue.removed", &[("title", title)]))).await
+}
@@ -0,0 +1,71 @@
+use poise::serenity_prelude as serenity;
+use serenity::{Channel, ChannelId};
+
+use crate::{
+    command_handler::{
+        command_handler::*,
+        command_return::CommandReturn,
+    }, connection_handler::*, error::BotError, i18n::{t, tr, Lang}, utils::{guild_queue::get_guild_queue, session_store, youtube_dl::prepare_source}
+};
+
+use tracing::warn;
+
+/// resume the queue saved before restart
+#[poise::command(slash_command, guild_only, rename = "resume-session", check = "in_voice_channel", check = "is_host", check = "media_ready")]
+pub async fn resume_session(ctx: CommandContext<'_>) -> Result<(), Error> {
+    let result = run(ctx).await?;
+    send_return(ctx, result).await
+}
+
+async fn run(ctx: CommandContext<'_>) -> Result<CommandReturn, BotError> {
+    let (ctx, command) = (ctx.serenity_context(), ctx.interaction);
+
+    let guild_id = command.guild_id.unwrap();
+    let lang = Lang::of(Some(guild_id));
+    let session = match session_store::load(guild_id).await {
+        Some(session) if !session.tracks.is_empty() => session,
+        _ => return Ok(CommandReturn::String(t(lang, "resume.nothing"))),
+    };
+
+    let guild_queue = get_guild_queue(ctx, guild_id).await;
+    if !guild_queue.is_empty().await {
+        return Ok(CommandReturn::String(t(lang, "resume.busy")));
+    }
+
+    establish_connection(ctx, command).await?;
+    guild_queue.claim_host(command.user.id);
+
+    // board of the saved session takes priority over the invoking channel
+    if guild_queue.channel().await.is_none() {
+        if let Some(channel_id) = session.channel.filter(|&id| id != 0).map(ChannelId::new) {
+            if let Ok(Channel::Guild(channel)) = channel_id.to_channel(&ctx.http).await {
+                guild_queue.register_channel(&channel).await?;
+                guild_queue.restore_scores(session.score.clone());
+            }
+        }
+    }
+    guild_queue.register_channel_if_empty(command.channel_id).await;
+
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let mut restored = 0;
+    for source in &session.tracks {
+        let Some(handler_lock) = manager.get(guild_id) else {
+            break;
+        };
+        match prepare_source(source).await {
+            Ok((src, meta)) => {
+                let mut handler = handler_lock.lock().await;
+                match guild_queue.add_source(src, meta, &mut handler, true).await {
+                    Ok(_) => restored += 1,
+                    Err(why) => warn!("Failed to queue {}: {:?}", source.url, why),
+                }
+            },
+            Err(why) => warn!("Failed to prepare {}: {:?}", source.url, why),
+        }
+    }
+
+    Ok(CommandReturn::String(tr(lang, "resume.restored", &[
+        ("total", session.tracks.len().to_string()),
+        ("count", restored.to_string()),
+    ])))
+}
@@ -0,0 +1,56 @@
+use poise::serenity_prelude as serenity;
+use serenity::{Mentionable, Role};
+
+use crate::{
+    command_handler::{
+        command_handler::*,
+        command_return::CommandReturn,
+    },
+    i18n::{t, tr, Lang},
+    utils::guild_settings,
+};
+
+/// set the roles allowed to control games
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("host", "dj"),
+    subcommand_required,
+    default_member_permissions = "MANAGE_GUILD"
+)]
+pub async fn roles(_ctx: CommandContext<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// set the role which may control any game
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD")]
+pub async fn host(
+    ctx: CommandContext<'_>,
+    #[description = "host role, clears it when empty"] role: Option<Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    let role_id = role.as_ref().map(|role| role.id);
+    guild_settings::update(guild_id, |settings| settings.host_role = role_id).await;
+
+    send_return(ctx, CommandReturn::String(role_message(Lang::of(Some(guild_id)), "roles.host", role))).await
+}
+
+/// set the role which may add and skip songs
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD")]
+pub async fn dj(
+    ctx: CommandContext<'_>,
+    #[description = "DJ role, clears it when empty"] role: Option<Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    let role_id = role.as_ref().map(|role| role.id);
+    guild_settings::update(guild_id, |settings| settings.dj_role = role_id).await;
+
+    send_return(ctx, CommandReturn::String(role_message(Lang::of(Some(guild_id)), "roles.dj", role))).await
+}
+
+fn role_message(lang: Lang, key: &str, role: Option<Role>) -> String {
+    match role {
+        Some(role) => tr(lang, &format!("{}.set", key), &[("role", role.mention().to_string())]),
+        None => t(lang, &format!("{}.cleared", key)),
+    }
+}
@@ -0,0 +1,115 @@
+use poise::serenity_prelude as serenity;
+use serenity::{GuildChannel, User};
+
+use crate::{
+    command_handler::{
+        command_handler::*,
+        command_return::CommandReturn,
+    },
+    connection_handler::ConnectionErrorCode,
+    error::BotError,
+    i18n::{t, tr, Lang},
+    utils::{guild_queue::find_guild_queue, idle_supervisor::listeners},
+};
+
+/// play in teams
+#[poise::command(slash_command, guild_only, subcommands("create", "add", "balance", "clear"), subcommand_required)]
+pub async fn team(_ctx: CommandContext<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// create a team
+#[poise::command(slash_command, guild_only, check = "is_host")]
+pub async fn create(
+    ctx: CommandContext<'_>,
+    #[description = "team name"] team: String,
+    #[description = "channel only this team answers in"]
+    #[channel_types("Text")]
+    channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    let lang = Lang::of(Some(guild_id));
+    let guild_queue = running_session(ctx.serenity_context(), guild_id).await?;
+
+    let created = guild_queue.create_team(team.clone(), channel.map(|channel| channel.id)).await;
+    if !created {
+        return Err(BotError::Parse("team.exists".to_owned()));
+    }
+    send_return(ctx, CommandReturn::String(tr(lang, "team.created", &[("team", team)]))).await
+}
+
+async fn autocomplete_team(ctx: CommandContext<'_>, partial: &str) -> Vec<String> {
+    let Some(guild_id) = ctx.interaction.guild_id else {
+        return Vec::new();
+    };
+    let Some(guild_queue) = find_guild_queue(ctx.serenity_context(), guild_id).await else {
+        return Vec::new();
+    };
+    guild_queue.team_names().await
+        .into_iter()
+        .filter(|name| name.to_lowercase().contains(&partial.to_lowercase()))
+        .collect()
+}
+
+/// put a member in a team
+#[poise::command(slash_command, guild_only, check = "is_host")]
+pub async fn add(
+    ctx: CommandContext<'_>,
+    #[description = "team name"]
+    #[autocomplete = "autocomplete_team"]
+    team: String,
+    #[description = "member to add"] user: User,
+) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    let lang = Lang::of(Some(guild_id));
+    let name = user.nick_in(ctx.serenity_context(), guild_id).await.unwrap_or(user.name.clone());
+
+    let guild_queue = running_session(ctx.serenity_context(), guild_id).await?;
+    if !guild_queue.add_to_team(team.clone(), user.id, name.clone()).await {
+        return Err(BotError::Parse("team.not_found".to_owned()));
+    }
+    send_return(ctx, CommandReturn::String(tr(lang, "team.added", &[("user", name), ("team", team)]))).await
+}
+
+/// split the voice channel members into teams at random
+#[poise::command(slash_command, guild_only, check = "is_host")]
+pub async fn balance(
+    ctx: CommandContext<'_>,
+    #[description = "number of teams, used when there are no teams yet"]
+    #[min = 2]
+    #[max = 10]
+    count: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    let lang = Lang::of(Some(guild_id));
+    let serenity_ctx = ctx.serenity_context();
+
+    let listeners = listeners(serenity_ctx, guild_id).await
+        .ok_or(BotError::Connection(ConnectionErrorCode::NotConnected))?;
+    // CacheRef is not Send
+    let users = match guild_id.to_guild_cached(&serenity_ctx.cache) {
+        Some(guild) => listeners.into_iter()
+            .map(|user_id| {
+                let name = guild.members.get(&user_id)
+                    .map(|member| member.display_name().to_owned())
+                    .unwrap_or(user_id.to_string());
+                (user_id, name)
+            })
+            .collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+
+    let guild_queue = running_session(serenity_ctx, guild_id).await?;
+    let count = count.unwrap_or(2) as usize;
+    guild_queue.balance_teams(count, users, move |index| tr(lang, "team.default_name", &[("index", index.to_string())]));
+    send_return(ctx, CommandReturn::String(t(lang, "team.balanced"))).await
+}
+
+/// disband every team
+#[poise::command(slash_command, guild_only, check = "is_host")]
+pub async fn clear(ctx: CommandContext<'_>) -> Result<(), Error> {
+    let guild_id = ctx.interaction.guild_id.unwrap();
+    let guild_queue = running_session(ctx.serenity_context(), guild_id).await?;
+    guild_queue.clear_teams();
+    send_return(ctx, CommandReturn::String(t(Lang::of(Some(guild_id)), "team.cleared"))).await
+}
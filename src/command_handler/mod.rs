@@ -1,3 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod command_handler;
 pub mod command_return;
-pub mod commands;
\ No newline at end of file
+pub mod commands;
+pub mod registration;
\ No newline at end of file
@@ -0,0 +1,153 @@
+use poise::serenity_prelude as serenity;
+use serenity::{Command, CommandId, CreateCommand, GuildId, Http};
+
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{command_handler::command_handler::*, config};
+
+/// Keys Discord reports on a registered command which are not part of `CreateCommand`.
+const IGNORED_KEYS: [&str; 8] = [
+    "id", "application_id", "guild_id", "version",
+    "name_localized", "description_localized", "contexts", "integration_types",
+];
+
+/// Where commands are registered.
+enum Scope {
+    Global,
+    Guild(GuildId),
+}
+
+/// Brings the commands registered on Discord in line with [`commands`].
+///
+/// Commands are registered globally. When `dev_guild_id` is configured they are
+/// registered to that guild only, which takes effect immediately.
+/// Commands left in other scopes, such as the per-guild ones made by the old `/launch`, are deleted.
+pub async fn sync_commands(http: &Http, guilds: &[GuildId]) {
+    let desired = poise::builtins::create_application_commands(&commands());
+    let dev_guild = config::get().dev_guild_id.map(GuildId::new);
+
+    match dev_guild {
+        Some(dev_guild) => {
+            info!("Dev mode, registering commands to {}", dev_guild);
+            sync_scope(http, Scope::Global, &[]).await;
+            for &guild_id in guilds.iter().filter(|&&guild_id| guild_id != dev_guild) {
+                sync_scope(http, Scope::Guild(guild_id), &[]).await;
+            }
+            sync_scope(http, Scope::Guild(dev_guild), &desired).await;
+        },
+        None => {
+            sync_scope(http, Scope::Global, &desired).await;
+            for &guild_id in guilds {
+                sync_scope(http, Scope::Guild(guild_id), &[]).await;
+            }
+        },
+    }
+}
+
+async fn sync_scope(http: &Http, scope: Scope, desired: &[CreateCommand]) {
+    let registered = match &scope {
+        Scope::Global => Command::get_global_commands_with_localizations(http).await,
+        Scope::Guild(guild_id) => guild_id.get_commands_with_localizations(http).await,
+    };
+    let registered = match registered {
+        Ok(registered) => registered,
+        Err(why) => {
+            warn!("Failed to get registered commands: {:?}", why);
+            return;
+        }
+    };
+
+    let is_guild = matches!(scope, Scope::Guild(_));
+    for builder in desired {
+        let wanted = normalize(to_value(builder, is_guild));
+        let name = wanted.get("name").and_then(Value::as_str).unwrap_or_default();
+
+        let result = match registered.iter().find(|command| command.name == name) {
+            Some(command) if normalize(to_value(command, is_guild)) == wanted => continue,
+            Some(command) => {
+                info!("Editing command {}", name);
+                edit(http, &scope, command.id, builder.clone()).await
+            },
+            None => {
+                info!("Creating command {}", name);
+                create(http, &scope, builder.clone()).await
+            },
+        };
+        if let Err(why) = result {
+            warn!("Failed to register command {}: {:?}", name, why);
+        }
+    }
+
+    for command in &registered {
+        let wanted = desired.iter()
+            .any(|builder| to_value(builder, is_guild).get("name").and_then(Value::as_str) == Some(command.name.as_str()));
+        if wanted {
+            continue;
+        }
+        info!("Deleting command {}", command.name);
+        let result = match &scope {
+            Scope::Global => Command::delete_global_command(http, command.id).await,
+            Scope::Guild(guild_id) => guild_id.delete_command(http, command.id).await,
+        };
+        if let Err(why) = result {
+            warn!("Failed to delete command {}: {:?}", command.name, why);
+        }
+    }
+}
+
+async fn create(http: &Http, scope: &Scope, builder: CreateCommand) -> serenity::Result<Command> {
+    match scope {
+        Scope::Global => Command::create_global_command(http, builder).await,
+        Scope::Guild(guild_id) => guild_id.create_command(http, builder).await,
+    }
+}
+
+async fn edit(http: &Http, scope: &Scope, id: CommandId, builder: CreateCommand) -> serenity::Result<Command> {
+    match scope {
+        Scope::Global => Command::edit_global_command(http, id, builder).await,
+        Scope::Guild(guild_id) => guild_id.edit_command(http, id, builder).await,
+    }
+}
+
+/// Serializes a command, keeping only the fields `CreateCommand` can set.
+fn to_value(command: &impl serde::Serialize, is_guild: bool) -> Value {
+    let mut value = serde_json::to_value(command).unwrap_or_default();
+    if let Value::Object(map) = &mut value {
+        for key in IGNORED_KEYS {
+            map.remove(key);
+        }
+        // only meaningful for global commands
+        if is_guild {
+            map.remove("dm_permission");
+        }
+        // `CreateCommand` leaves the type empty for slash commands
+        map.entry("type").or_insert(Value::from(1));
+    }
+    value
+}
+
+/// Drops empty and default values so that a builder and a registered command compare equal.
+fn normalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, normalize(value)))
+                .filter(|(key, value)| !is_default(value) || key == "dm_permission")
+                .collect()
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(normalize).collect()),
+        // permissions are strings in builders and may be numbers elsewhere
+        Value::Number(number) => Value::String(number.to_string()),
+        value => value,
+    }
+}
+
+fn is_default(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::Array(values) => values.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    }
+}
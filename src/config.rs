@@ -0,0 +1,340 @@
+//! Settings of the bot, layered from defaults, a TOML file, env vars and command line flags.
+//!
+//! Every key can be given in each layer, a later layer wins:
+//! - `config.toml`, or the file of `--config` or `QUIZ_BOT_CONFIG`
+//! - `QUIZ_BOT_<KEY>`, e.g. `QUIZ_BOT_CACHE_DIR`
+//! - `--<key>`, e.g. `--cache-dir ./cache`
+//!
+//! [`load`] validates the result so that a bad value stops the bot at startup, not on first use.
+
+use quiz_core::clip;
+use serde::Deserialize;
+use std::{
+    fmt,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_FILE: &str = "config.toml";
+const ENV_PREFIX: &str = "QUIZ_BOT_";
+
+/// Every key, as written in the file.
+pub const KEYS: [&str; 18] = [
+    "cache_dir",
+    "data_dir",
+    "no_sound",
+    "yt_dlp",
+    "ffmpeg",
+    "ffprobe",
+    "require_tools",
+    "ytdl_format",
+    "max_downloads",
+    "idle_timeout",
+    "idle_check_interval",
+    "log_level",
+    "log_format",
+    "metrics_addr",
+    "admin_api_addr",
+    "admin_api_token",
+    "dashboard_addr",
+    "dev_guild_id",
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where downloads are kept, and cut clips until shutdown.
+    pub cache_dir: PathBuf,
+    /// Where settings, sessions and quiz sets are saved.
+    pub data_dir: PathBuf,
+    /// Silent track played between songs.
+    pub no_sound: PathBuf,
+    pub yt_dlp: String,
+    pub ffmpeg: String,
+    pub ffprobe: String,
+    /// Whether to stop at startup when a tool is missing, rather than disable the commands playing songs.
+    pub require_tools: bool,
+    /// Format passed to `yt-dlp -f`.
+    pub ytdl_format: String,
+    /// Downloads run at the same time, further ones wait.
+    pub max_downloads: usize,
+    /// Seconds the queue may stay empty before the bot leaves.
+    pub idle_timeout: u64,
+    /// Seconds between checks for an empty queue or an empty voice channel.
+    pub idle_check_interval: u64,
+    /// Filter in the syntax of `RUST_LOG`.
+    pub log_level: String,
+    pub log_format: LogFormat,
+    pub metrics_addr: SocketAddr,
+    /// Must be a loopback address.
+    pub admin_api_addr: SocketAddr,
+    /// Bearer token the admin API asks for, anyone on the machine may call it when not set.
+    pub admin_api_token: Option<String>,
+    /// Must be a loopback address.
+    pub dashboard_addr: SocketAddr,
+    /// Guild to register the commands to instead of globally.
+    pub dev_guild_id: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cache_dir: "./target/tmp/".into(),
+            data_dir: "./data/".into(),
+            no_sound: "./target/NO_SOUND.webm".into(),
+            yt_dlp: clip::DEFAULT_YT_DLP.to_owned(),
+            ffmpeg: clip::DEFAULT_FFMPEG.to_owned(),
+            ffprobe: "ffprobe".to_owned(),
+            require_tools: true,
+            ytdl_format: clip::DEFAULT_FORMAT.to_owned(),
+            max_downloads: 4,
+            idle_timeout: 5 * 60,
+            idle_check_interval: 30,
+            log_level: "info".to_owned(),
+            log_format: LogFormat::Text,
+            metrics_addr: ([127, 0, 0, 1], 9898).into(),
+            admin_api_addr: ([127, 0, 0, 1], 9899).into(),
+            admin_api_token: None,
+            dashboard_addr: ([127, 0, 0, 1], 9900).into(),
+            dev_guild_id: None,
+        }
+    }
+}
+
+/// Where a value which could not be parsed came from, the file reports its own errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    Env(String),
+    Flag(String),
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layer::Env(name) => write!(f, "env var {}", name),
+            Layer::Flag(flag) => write!(f, "flag {}", flag),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// `--help` was given.
+    Help,
+    /// The command line could not be understood.
+    Usage(String),
+    /// The file could not be read or parsed.
+    File(PathBuf, String),
+    /// A key is unknown or its value could not be parsed.
+    Value { layer: Layer, key: String, reason: String },
+    /// The values parsed but cannot be used.
+    Invalid { key: &'static str, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Help => write!(f, "{}", usage()),
+            ConfigError::Usage(reason) => write!(f, "{}\n\n{}", reason, usage()),
+            ConfigError::File(path, reason) => write!(f, "could not load {}: {}", path.display(), reason),
+            ConfigError::Value { layer, key, reason } => write!(f, "invalid `{}` in {}: {}", key, layer, reason),
+            ConfigError::Invalid { key, reason } => write!(f, "invalid `{}`: {}", key, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn usage() -> String {
+    let flags = KEYS.iter()
+        .map(|key| format!("  --{:<20} {}{}", flag_name(key), ENV_PREFIX, key.to_uppercase()))
+        .collect::<Vec<String>>()
+        .join("\n");
+    format!(
+        "usage: quiz_bot [--config <file>] [--<key> <value>]...\n\n\
+        Keys, also read from {} and the env vars on the right:\n{}",
+        DEFAULT_FILE, flags,
+    )
+}
+
+fn flag_name(key: &str) -> String {
+    key.replace('_', "-")
+}
+
+impl Config {
+    /// Sets `key` from its text in an env var or a flag.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String>
+        where
+            T::Err: fmt::Display,
+        {
+            value.trim().parse().map_err(|why: T::Err| format!("'{}': {}", value, why))
+        }
+
+        match key {
+            "cache_dir" => self.cache_dir = value.into(),
+            "data_dir" => self.data_dir = value.into(),
+            "no_sound" => self.no_sound = value.into(),
+            "yt_dlp" => self.yt_dlp = value.to_owned(),
+            "ffmpeg" => self.ffmpeg = value.to_owned(),
+            "ffprobe" => self.ffprobe = value.to_owned(),
+            "require_tools" => self.require_tools = parse(value)?,
+            "ytdl_format" => self.ytdl_format = value.to_owned(),
+            "max_downloads" => self.max_downloads = parse(value)?,
+            "idle_timeout" => self.idle_timeout = parse(value)?,
+            "idle_check_interval" => self.idle_check_interval = parse(value)?,
+            "log_level" => self.log_level = value.to_owned(),
+            "log_format" => self.log_format = match value.trim() {
+                "text" => LogFormat::Text,
+                "json" => LogFormat::Json,
+                _ => return Err(format!("'{}', expected text or json", value)),
+            },
+            "metrics_addr" => self.metrics_addr = parse(value)?,
+            "admin_api_addr" => self.admin_api_addr = parse(value)?,
+            "admin_api_token" => self.admin_api_token = match value.trim() {
+                "" => None,
+                value => Some(value.to_owned()),
+            },
+            "dashboard_addr" => self.dashboard_addr = parse(value)?,
+            // empty or 0 registers globally
+            "dev_guild_id" => self.dev_guild_id = match value.trim() {
+                "" | "0" => None,
+                value => Some(parse(value)?),
+            },
+            _ => return Err("unknown key".to_owned()),
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |key, reason: String| Err(ConfigError::Invalid { key, reason });
+
+        if let Err(why) = EnvFilter::try_new(&self.log_level) {
+            return invalid("log_level", why.to_string());
+        }
+        if self.max_downloads == 0 {
+            return invalid("max_downloads", "must be at least 1".to_owned());
+        }
+        if self.idle_check_interval == 0 {
+            return invalid("idle_check_interval", "must be at least 1".to_owned());
+        }
+        for (key, command) in [("yt_dlp", &self.yt_dlp), ("ffmpeg", &self.ffmpeg), ("ffprobe", &self.ffprobe)] {
+            if command.trim().is_empty() {
+                return invalid(key, "must not be empty".to_owned());
+            }
+        }
+        for (key, addr) in [
+            ("metrics_addr", self.metrics_addr),
+            ("admin_api_addr", self.admin_api_addr),
+            ("dashboard_addr", self.dashboard_addr),
+        ] {
+            if !addr.ip().is_loopback() {
+                return invalid(key, format!("{} is not a loopback address", addr));
+            }
+        }
+        for (key, dir) in [("cache_dir", &self.cache_dir), ("data_dir", &self.data_dir)] {
+            if let Err(why) = std::fs::create_dir_all(dir) {
+                return invalid(key, format!("could not create {}: {}", dir.display(), why));
+            }
+        }
+        Ok(())
+    }
+}
+
+// flag as given, key and value
+type FlagValue = (String, String, String);
+
+/// Splits the command line into the config file and the keys set by flags.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(Option<PathBuf>, Vec<FlagValue>), ConfigError> {
+    let mut file = None;
+    let mut values = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Err(ConfigError::Help);
+        }
+        let Some(flag) = arg.strip_prefix("--") else {
+            return Err(ConfigError::Usage(format!("unexpected argument {}", arg)));
+        };
+        let (name, value) = match flag.split_once('=') {
+            Some((name, value)) => (name.to_owned(), value.to_owned()),
+            None => {
+                let value = args.next()
+                    .ok_or_else(|| ConfigError::Usage(format!("--{} needs a value", flag)))?;
+                (flag.to_owned(), value)
+            },
+        };
+        if name == "config" {
+            file = Some(value.into());
+            continue;
+        }
+        let key = name.replace('-', "_");
+        if !KEYS.contains(&key.as_str()) {
+            return Err(ConfigError::Usage(format!("unknown flag --{}", name)));
+        }
+        values.push((format!("--{}", name), key, value));
+    }
+    Ok((file, values))
+}
+
+fn read_file(path: &Path) -> Result<Config, ConfigError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|why| ConfigError::File(path.to_owned(), why.to_string()))?;
+    toml::from_str(&text).map_err(|why| ConfigError::File(path.to_owned(), why.to_string()))
+}
+
+/// Reads the configuration from the command line `args` after the program name, the env vars
+/// given by `env` and the config file they point to.
+pub fn load(args: impl Iterator<Item = String>, env: impl Fn(&str) -> Option<String>) -> Result<Config, ConfigError> {
+    let (file, flags) = parse_args(args)?;
+
+    let file = file.or_else(|| env(&format!("{}CONFIG", ENV_PREFIX)).map(PathBuf::from));
+    let mut config = match file {
+        Some(path) => read_file(&path)?,
+        None if Path::new(DEFAULT_FILE).is_file() => read_file(Path::new(DEFAULT_FILE))?,
+        None => Config::default(),
+    };
+
+    for key in KEYS {
+        let name = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+        if let Some(value) = env(&name) {
+            config.set(key, &value).map_err(|reason| ConfigError::Value {
+                layer: Layer::Env(name),
+                key: key.to_owned(),
+                reason,
+            })?;
+        }
+    }
+    for (flag, key, value) in flags {
+        config.set(&key, &value).map_err(|reason| ConfigError::Value {
+            layer: Layer::Flag(flag),
+            key,
+            reason,
+        })?;
+    }
+
+    config.validate()?;
+    Ok(config)
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Makes `config` the one returned by [`get`], once at startup.
+pub fn init(config: Config) {
+    if CONFIG.set(config).is_err() {
+        panic!("Configuration was initialized twice");
+    }
+}
+
+/// The configuration given to [`init`], or the defaults before it.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
@@ -1,22 +1,18 @@
 use poise::serenity_prelude as serenity;
-use serenity::{
-    async_trait, CreateCommand, Context, CommandInteraction, CommandDataOption, VoiceState,
-    UserId
-};
+use serenity::{Context, CommandInteraction, VoiceState, UserId, GuildId};
 
-use songbird::{error::JoinError, tracks::TrackQueue, TrackEvent};
-use tokio::time::error::Elapsed;
+use songbird::error::JoinError;
 
-use std::{collections::HashMap, sync::Arc};
+use std::collections::HashMap;
 
-use crate::{event_handler::track_event_handler::TrackEndNotifier, utils::guild_queue::GuildQueue, GuildQueueKey};
+use crate::utils::{guild_queue::remove_guild_queue, idle_supervisor::spawn_idle_supervisor, session_store};
 
 #[derive(Debug)]
 pub enum ConnectionErrorCode {
     AlreadyInUse,
     JoinVoiceChannelFirst,
-    VoiceChannelNotFound,
     ServerNotFound,
+    NotConnected,
     JoinError(JoinError)
 }
 
@@ -28,12 +24,22 @@ pub enum ConnectionSuccessCode {
 pub async fn establish_connection(ctx: &Context, command: &CommandInteraction) -> Result<ConnectionSuccessCode, ConnectionErrorCode> {
     let guild_id = command.guild_id.unwrap();
 
+    let result = connect_user_channel(ctx, command).await;
+    if let Ok(ConnectionSuccessCode::NewConnection) = result {
+        spawn_idle_supervisor(ctx.clone(), guild_id);
+    }
+    result
+}
+
+async fn connect_user_channel(ctx: &Context, command: &CommandInteraction) -> Result<ConnectionSuccessCode, ConnectionErrorCode> {
+    let guild_id = command.guild_id.unwrap();
+
     let user_channel = {
         // CacheRef is not Send
-        let voice_states: &HashMap<UserId, VoiceState> = &guild_id
+        let guild = guild_id
             .to_guild_cached(ctx)
-            .unwrap()
-            .voice_states;
+            .ok_or(ConnectionErrorCode::ServerNotFound)?;
+        let voice_states: &HashMap<UserId, VoiceState> = &guild.voice_states;
         voice_states
             .get(&command.user.id)
             .and_then(|voice_state| voice_state.channel_id)
@@ -61,11 +67,7 @@ pub async fn establish_connection(ctx: &Context, command: &CommandInteraction) -
             }
         } else {
             match manager.join(guild_id, user_channel).await {
-                Ok(_/*handler_lock*/) => {
-                    // let mut handler = handler_lock.lock().await;
-                    // handler.add_global_event(TrackEvent::End.into(), TrackEndNotifier);
-                    Ok(ConnectionSuccessCode::NewConnection)
-                },
+                Ok(_) => Ok(ConnectionSuccessCode::NewConnection),
                 Err(why) => Err(ConnectionErrorCode::JoinError(why))
             }
         }
@@ -74,18 +76,32 @@ pub async fn establish_connection(ctx: &Context, command: &CommandInteraction) -
     }
 }
 
-pub async fn terminate_connection(ctx: &Context, command: &CommandInteraction) {
-    let guild_id = command.guild_id.unwrap();
+pub async fn terminate_connection(ctx: &Context, guild_id: GuildId) -> Result<(), ConnectionErrorCode> {
     let voice_manager = songbird::get(ctx)
         .await
         .expect("Songbird Voice client placed in at initialisation.")
         .clone();
 
-    let handler_lock = voice_manager.get(guild_id).expect("Guild Not Found");
-    handler_lock
-        .lock()
-        .await
+    let handler_lock = voice_manager.get(guild_id).ok_or(ConnectionErrorCode::NotConnected)?;
+    let mut handler = handler_lock.lock().await;
+    if handler.current_channel().is_none() {
+        return Err(ConnectionErrorCode::NotConnected);
+    }
+    handler
         .leave()
         .await
-        .expect("Disconnect Fail");
-}
\ No newline at end of file
+        .map_err(ConnectionErrorCode::JoinError)?;
+    Ok(())
+}
+
+/// Ends the voice session of the guild.
+///
+/// Stops the queue, posts the final scores to the board thread and archives it,
+/// then leaves the voice channel. The `GuildQueue` is removed so the next session starts fresh.
+pub async fn end_session(ctx: &Context, guild_id: GuildId) -> Result<(), ConnectionErrorCode> {
+    if let Some(guild_queue) = remove_guild_queue(ctx, guild_id).await {
+        guild_queue.end().await;
+    }
+    session_store::remove(guild_id).await;
+    terminate_connection(ctx, guild_id).await
+}
@@ -0,0 +1,182 @@
+//! Local web UI to manage the quiz sets of `quiz_store`.
+//!
+//! Built with the `dashboard` feature, [`serve`] hosts the page and its JSON API on `dashboard_addr`.
+//! Like the admin API it has no authentication, so it only listens on a loopback address.
+//!
+//! - `GET /api/sets`, `POST /api/sets`: list or create quiz sets
+//! - `GET`, `PUT`, `DELETE /api/sets/:id`: read, replace or delete a quiz set
+//! - `GET /api/sets/:id/csv`: the set as a csv for `/playlist`
+//! - `GET /api/metadata?url=`: title and length of a video from yt-dlp
+//! - `GET /api/preview?url=&start=&duration=`: the clip cut by `ytdl_optioned`
+
+#[cfg(feature = "dashboard")]
+mod enabled {
+    use axum::{
+        extract::{Path, Query},
+        http::{header, StatusCode},
+        response::{Html, IntoResponse, Response},
+        routing::get,
+        Json, Router,
+    };
+    use serde::{Deserialize, Serialize};
+    use tracing::{info, warn};
+
+    use crate::{
+        config,
+        utils::{
+            quiz_store::{self, QuizSet},
+            url_checker::url_checker,
+            youtube_dl::{fetch_output, ytdl_optioned},
+        },
+    };
+
+    const PAGE: &str = include_str!("../assets/dashboard.html");
+
+    type ApiError = (StatusCode, String);
+
+    fn internal(why: impl std::fmt::Debug) -> ApiError {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", why))
+    }
+
+    fn video_id(url: &str) -> Result<String, ApiError> {
+        url_checker(url).ok_or((StatusCode::BAD_REQUEST, format!("not a youtube url: {}", url)))
+    }
+
+    #[derive(Serialize)]
+    struct SetSummary {
+        id: u64,
+        name: String,
+        len: usize,
+    }
+
+    #[derive(Deserialize)]
+    struct NewSet {
+        name: String,
+    }
+
+    #[derive(Deserialize)]
+    struct MetadataQuery {
+        url: String,
+    }
+
+    #[derive(Serialize)]
+    struct Metadata {
+        url: String,
+        title: Option<String>,
+        length: Option<i64>,
+    }
+
+    #[derive(Deserialize)]
+    struct PreviewQuery {
+        url: String,
+        #[serde(default)]
+        start: i64,
+        #[serde(default)]
+        duration: i64,
+    }
+
+    async fn page() -> Html<&'static str> {
+        Html(PAGE)
+    }
+
+    async fn list_sets() -> Json<Vec<SetSummary>> {
+        let sets = quiz_store::load_all().await
+            .into_iter()
+            .map(|set| SetSummary { id: set.id, name: set.name, len: set.entries.len() })
+            .collect();
+        Json(sets)
+    }
+
+    async fn create_set(Json(new): Json<NewSet>) -> Result<Json<QuizSet>, ApiError> {
+        quiz_store::create(new.name).await.map(Json).map_err(internal)
+    }
+
+    async fn load_set(id: u64) -> Result<QuizSet, ApiError> {
+        quiz_store::load(id).await.ok_or((StatusCode::NOT_FOUND, format!("no quiz set {}", id)))
+    }
+
+    async fn show_set(Path(id): Path<u64>) -> Result<Json<QuizSet>, ApiError> {
+        load_set(id).await.map(Json)
+    }
+
+    async fn replace_set(Path(id): Path<u64>, Json(mut set): Json<QuizSet>) -> Result<Json<QuizSet>, ApiError> {
+        load_set(id).await?;
+        set.id = id;
+        // entries keep the video id, but a full url may be pasted in
+        for entry in &mut set.entries {
+            if let Some(id) = url_checker(&entry.url) {
+                entry.url = id;
+            }
+        }
+        quiz_store::save(&set).await.map_err(internal)?;
+        Ok(Json(set))
+    }
+
+    async fn delete_set(Path(id): Path<u64>) -> StatusCode {
+        if quiz_store::remove(id).await {
+            StatusCode::NO_CONTENT
+        } else {
+            StatusCode::NOT_FOUND
+        }
+    }
+
+    async fn export_set(Path(id): Path<u64>) -> Result<Response, ApiError> {
+        let set = load_set(id).await?;
+        let disposition = format!("attachment; filename=\"{}.csv\"", id);
+        Ok((
+            [(header::CONTENT_TYPE, "text/csv; charset=utf-8".to_owned()), (header::CONTENT_DISPOSITION, disposition)],
+            set.to_csv(),
+        ).into_response())
+    }
+
+    async fn metadata(Query(query): Query<MetadataQuery>) -> Result<Json<Metadata>, ApiError> {
+        let url = video_id(&query.url)?;
+        let output = fetch_output(&url).await.map_err(|why| (StatusCode::BAD_GATEWAY, why.to_string()))?;
+        Ok(Json(Metadata { url, title: output.title, length: output.duration }))
+    }
+
+    async fn preview(Query(query): Query<PreviewQuery>) -> Result<Response, ApiError> {
+        let url = video_id(&query.url)?;
+        let (path, _) = ytdl_optioned(&url, query.start, query.duration).await
+            .map_err(|why| (StatusCode::BAD_GATEWAY, why.to_string()))?;
+        let audio = tokio::fs::read(&path).await.map_err(internal)?;
+        // the clip is opus in the container of the downloaded audio
+        let content_type = match path.rsplit('.').next() {
+            Some("webm") => "audio/webm",
+            Some("m4a" | "mp4") => "audio/mp4",
+            Some("ogg" | "opus") => "audio/ogg",
+            _ => "application/octet-stream",
+        };
+        Ok(([(header::CONTENT_TYPE, content_type)], audio).into_response())
+    }
+
+    pub async fn serve() {
+        // checked to be a loopback address by `config::load`
+        let addr = config::get().dashboard_addr;
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(why) => {
+                warn!("Failed to bind dashboard to {}: {:?}", addr, why);
+                return;
+            }
+        };
+        info!("Serving dashboard on http://{}", addr);
+
+        let app = Router::new()
+            .route("/", get(page))
+            .route("/api/sets", get(list_sets).post(create_set))
+            .route("/api/sets/:id", get(show_set).put(replace_set).delete(delete_set))
+            .route("/api/sets/:id/csv", get(export_set))
+            .route("/api/metadata", get(metadata))
+            .route("/api/preview", get(preview));
+        if let Err(why) = axum::serve(listener, app).await {
+            warn!("Dashboard stopped: {:?}", why);
+        }
+    }
+}
+
+/// Serves the dashboard until the process exits.
+pub async fn serve() {
+    #[cfg(feature = "dashboard")]
+    enabled::serve().await;
+}
@@ -0,0 +1,111 @@
+use poise::serenity_prelude as serenity;
+use serenity::{Colour, CreateEmbed};
+
+use songbird::{input::AudioStreamError, tracks::ControlError};
+use std::fmt;
+
+use crate::{
+    connection_handler::ConnectionErrorCode,
+    i18n::{t, tr, Lang},
+};
+
+/// Every failure a command can run into.
+#[derive(Debug)]
+pub enum BotError {
+    /// Joining or leaving the voice channel failed.
+    Connection(ConnectionErrorCode),
+    /// Preparing or playing a track failed.
+    Media(String),
+    /// User input could not be understood. The reason may be a catalog key.
+    Parse(String),
+    /// Request to Discord failed.
+    Discord(serenity::Error),
+    /// The user or the bot lacks a permission. The reason may be a catalog key.
+    Permission(String),
+    /// The command is disabled, such as while a media tool is missing. The reason may be a catalog key.
+    Unavailable(String),
+}
+
+impl BotError {
+    /// Message shown to the user.
+    pub fn user_message(&self, lang: Lang) -> String {
+        match self {
+            BotError::Connection(code) => match code {
+                ConnectionErrorCode::JoinVoiceChannelFirst => t(lang, "error.voice.join_first"),
+                ConnectionErrorCode::AlreadyInUse => t(lang, "error.voice.in_use"),
+                ConnectionErrorCode::NotConnected => t(lang, "error.voice.not_connected"),
+                ConnectionErrorCode::ServerNotFound
+                | ConnectionErrorCode::JoinError(_) => t(lang, "error.voice.failed"),
+            },
+            BotError::Media(_) => t(lang, "error.media"),
+            BotError::Parse(reason) => tr(lang, "error.parse", &[("reason", t(lang, reason))]),
+            BotError::Discord(_) => t(lang, "error.discord"),
+            BotError::Permission(reason) => tr(lang, "error.permission", &[("reason", t(lang, reason))]),
+            BotError::Unavailable(reason) => tr(lang, "error.unavailable", &[("reason", t(lang, reason))]),
+        }
+    }
+
+    pub fn to_embed(&self, lang: Lang) -> CreateEmbed {
+        CreateEmbed::new()
+            .title(t(lang, "error.title"))
+            .description(self.user_message(lang))
+            .colour(Colour::RED)
+    }
+
+    /// Short name of the variant, used as a log field.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BotError::Connection(_) => "connection",
+            BotError::Media(_) => "media",
+            BotError::Parse(_) => "parse",
+            BotError::Discord(_) => "discord",
+            BotError::Permission(_) => "permission",
+            BotError::Unavailable(_) => "unavailable",
+        }
+    }
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::Connection(ConnectionErrorCode::JoinError(why)) => write!(f, "connection error: {}", why),
+            BotError::Connection(code) => write!(f, "connection error: {:?}", code),
+            BotError::Media(why) => write!(f, "media error: {}", why),
+            BotError::Parse(why) => write!(f, "parse error: {}", why),
+            BotError::Discord(why) => write!(f, "discord error: {}", why),
+            BotError::Permission(why) => write!(f, "permission error: {}", why),
+            BotError::Unavailable(why) => write!(f, "unavailable: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for BotError {}
+
+impl From<ConnectionErrorCode> for BotError {
+    fn from(code: ConnectionErrorCode) -> Self {
+        BotError::Connection(code)
+    }
+}
+
+impl From<AudioStreamError> for BotError {
+    fn from(why: AudioStreamError) -> Self {
+        BotError::Media(why.to_string())
+    }
+}
+
+impl From<ControlError> for BotError {
+    fn from(why: ControlError) -> Self {
+        BotError::Media(why.to_string())
+    }
+}
+
+impl From<serenity::Error> for BotError {
+    fn from(why: serenity::Error) -> Self {
+        match &why {
+            serenity::Error::Http(http) if http.status_code().map(|code| code.as_u16()) == Some(403) => {
+                BotError::Permission("error.permission.bot".to_owned())
+            },
+            _ => BotError::Discord(why),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use poise::serenity_prelude as serenity;
+use serenity::{
+    ComponentInteraction, ComponentInteractionDataKind, Context, CreateInteractionResponse,
+    CreateInteractionResponseMessage, GuildId,
+};
+
+use tracing::warn;
+
+use crate::{
+    command_handler::command_handler::running_session,
+    connection_handler::{end_session, ConnectionErrorCode},
+    error::BotError,
+    i18n::{t, tr, Lang},
+    utils::{
+        board::BoardControl,
+        choice_round::{ChoiceRound, Pick},
+        idle_supervisor::listeners,
+        permission::{self, Level},
+    },
+};
+
+/// Level a control needs, `None` when anyone listening may use it.
+fn required_level(control: BoardControl) -> Option<Level> {
+    match control {
+        BoardControl::PauseResume | BoardControl::Stop => Some(Level::Host),
+        BoardControl::Skip => Some(Level::Dj),
+        BoardControl::VoteSkip | BoardControl::Hint => None,
+    }
+}
+
+/// Dispatches a component interaction by its custom id.
+pub async fn handle_component(ctx: &Context, component: &ComponentInteraction) {
+    let Some(guild_id) = component.guild_id else {
+        return;
+    };
+    let custom_id = component.data.custom_id.as_str();
+
+    if let Some(control) = BoardControl::from_custom_id(custom_id) {
+        handle_control(ctx, component, guild_id, control).await;
+    } else if ChoiceRound::is_choice(custom_id) {
+        handle_choice(ctx, component, guild_id).await;
+    }
+}
+
+fn ephemeral_error(error: &BotError, lang: Lang) -> CreateInteractionResponse {
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .embed(error.to_embed(lang))
+            .ephemeral(true)
+    )
+}
+
+/// Handles a button press on the board.
+///
+/// The status message is updated through the board renderer, failures are replied only to the user who pressed.
+async fn handle_control(ctx: &Context, component: &ComponentInteraction, guild_id: GuildId, control: BoardControl) {
+    let lang = Lang::of(Some(guild_id));
+
+    let result = run_control(ctx, component, guild_id, control, lang).await;
+    let succeeded = result.is_ok();
+    let response = match result {
+        Ok(response) => response,
+        Err(error) => {
+            warn!(
+                control = ?control,
+                guild = %guild_id,
+                user = %component.user.id,
+                kind = error.kind(),
+                error = %error,
+                "Control failed"
+            );
+            ephemeral_error(&error, lang)
+        }
+    };
+    if let Err(why) = component.create_response(&ctx.http, response).await {
+        warn!(error = ?why, "Failed to respond to control");
+    }
+
+    // the board is archived by ending the session, so it is done after the response
+    if control == BoardControl::Stop && succeeded {
+        if let Err(why) = end_session(ctx, guild_id).await {
+            warn!(error = ?why, "Failed to stop game");
+        }
+    }
+}
+
+async fn run_control(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    guild_id: GuildId,
+    control: BoardControl,
+    lang: Lang,
+) -> Result<CreateInteractionResponse, BotError> {
+    let guild_queue = running_session(ctx, guild_id).await?;
+
+    if let Some(level) = required_level(control) {
+        let host = guild_queue.host().await;
+        let allowed = component.member.as_ref()
+            .is_some_and(|member| permission::allows(guild_id, member, host, level));
+        if !allowed {
+            return Err(BotError::Permission(level.denied_key().to_owned()));
+        }
+    }
+
+    match control {
+        BoardControl::PauseResume => {
+            guild_queue.toggle_pause().await?;
+        },
+        BoardControl::Skip => {
+            guild_queue.skip().await?;
+        },
+        BoardControl::VoteSkip => {
+            let listeners = listeners(ctx, guild_id).await
+                .ok_or(BotError::Connection(ConnectionErrorCode::NotConnected))?;
+            if !listeners.contains(&component.user.id) {
+                return Err(ConnectionErrorCode::JoinVoiceChannelFirst.into());
+            }
+            guild_queue.vote_skip(component.user.id, listeners.len()).await?;
+        },
+        BoardControl::Hint => {
+            let content = match guild_queue.hint().await {
+                Some(hint) => tr(lang, "hint.text", &[("hint", hint)]),
+                None => t(lang, "hint.none"),
+            };
+            return Ok(CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content(content).ephemeral(true)
+            ));
+        },
+        // the buttons are removed when the board finishes
+        BoardControl::Stop => {},
+    }
+
+    // the board renderer edits the status message
+    Ok(CreateInteractionResponse::Acknowledge)
+}
+
+/// Handles a pick on the menu of a multiple-choice round.
+async fn handle_choice(ctx: &Context, component: &ComponentInteraction, guild_id: GuildId) {
+    let lang = Lang::of(Some(guild_id));
+    let response = match run_choice(ctx, component, guild_id, lang).await {
+        Ok(content) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().content(content).ephemeral(true)
+        ),
+        Err(error) => {
+            warn!(guild = %guild_id, user = %component.user.id, kind = error.kind(), error = %error, "Pick failed");
+            ephemeral_error(&error, lang)
+        }
+    };
+    if let Err(why) = component.create_response(&ctx.http, response).await {
+        warn!(error = ?why, "Failed to respond to pick");
+    }
+}
+
+async fn run_choice(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    guild_id: GuildId,
+    lang: Lang,
+) -> Result<String, BotError> {
+    let option = match &component.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => values.first().and_then(|value| value.parse::<usize>().ok()),
+        _ => None,
+    };
+    let option = option.ok_or_else(|| BotError::Parse("choice.invalid".to_owned()))?;
+
+    let nickname = match &component.member {
+        Some(member) => member.display_name().to_owned(),
+        None => component.user.name.clone(),
+    };
+    let guild_queue = running_session(ctx, guild_id).await?;
+    let pick = guild_queue.pick(component.data.custom_id.clone(), component.user.id, nickname, option).await;
+
+    Ok(match pick {
+        Pick::Correct => t(lang, "choice.correct"),
+        Pick::Wrong => t(lang, "choice.wrong"),
+        Pick::AlreadyPicked => t(lang, "choice.already_picked"),
+        Pick::Closed => t(lang, "choice.closed"),
+    })
+}
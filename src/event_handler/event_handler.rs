@@ -1,148 +1,116 @@
 use poise::serenity_prelude::{
-    async_trait, Command, Interaction, Context, Ready, EventHandler, Message, VoiceState
+    async_trait, Interaction, Context, Ready, EventHandler, Message, VoiceState
 };
-use serenity::{all::CacheHttp, model::{channel, guild}};
-use crate::{command_handler::{
-    command_handler::*,
-    commands::*,
-}, utils::guild_queue::get_guild_queue, GuildQueueKey};
+use serenity::all::CacheHttp;
+use crate::{admin_api, command_handler::registration, event_handler::component_handler::handle_component, connection_handler::end_session, shutdown, utils::{guild_queue::{find_guild_queue, GuildQueue}, idle_supervisor::is_bot_alone}};
 
-use std::sync::Arc;
+use quiz_core::answer::Verdict;
+use tracing::{info, warn};
 
 pub struct DiscordEventHandler;
 
 #[async_trait]
 impl EventHandler for DiscordEventHandler {
     async fn ready(&self, ctx: Context, ready: Ready) {
-        println!("{} is connected!", ready.user.tag());
+        info!("{} is connected!", ready.user.tag());
 
-        let commands = Command::get_global_commands(&ctx.http).await.unwrap();
+        let guilds = ready.guilds.iter().map(|guild| guild.id).collect::<Vec<_>>();
+        registration::sync_commands(&ctx.http, &guilds).await;
 
-        match commands.iter().find(|command| {
-            "launch" == command.name.as_str()
-        }) {
-            Some(_) => (),
-            None => {
-                ctx.http.create_global_command(&launch::register()).await.unwrap();
-            }
-        };
+        shutdown::restore(&ctx).await;
+        admin_api::spawn(&ctx);
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         match interaction {
-            Interaction::Command(command) => match command.data.name.as_str() {
-                "launch" => launch::run(&ctx, command).await,
-                _ => execute_command(Arc::new(ctx), command).await,
-            },
+            // commands are dispatched by the poise framework
+            Interaction::Command(_) => {},
+            Interaction::Component(component) => handle_component(&ctx, &component).await,
             _ => {},
         };
     }
 
     async fn message(&self, ctx: Context, msg: Message) {
-        let guild_id = msg.guild_id.unwrap();
-        let guild_queue = get_guild_queue(&ctx, guild_id).await;
-        
-        if let Some(channel) = guild_queue.channel()
-            .filter(|channel| *channel == msg.channel_id.into())
-        {
-            if let Some((_, Some(keyword))) = guild_queue.current()
-                .map(|(_, meta)| ((), meta.keyword))
-                .filter(|(_, kw)| kw.as_ref().map_or(false, |kw| kw == msg.content.trim()))
-            {
-
-                let nickname = msg.member(ctx.http.clone()).await.unwrap().display_name().to_owned();
-                guild_queue.get_board().lock().await.add_score(nickname).await;
-                guild_queue.skip().unwrap();
-            }
-        }
+        let Some(guild_id) = msg.guild_id else {
+            return;
+        };
+        // chat in guilds without a session is not an answer
+        let Some(guild_queue) = find_guild_queue(&ctx, guild_id).await else {
+            return;
+        };
+        handle_answer(&ctx, &guild_queue, &msg).await;
     }
 
     async fn voice_state_update(&self, ctx: Context, old: Option<VoiceState>, new: VoiceState) {
-        let guild_id = new.guild_id.unwrap();
-        let cache = ctx.cache().unwrap().clone();
-        let http = ctx.http.clone();
-        let guild_name = guild_id.name(cache.clone()).unwrap();
-        
-        let (nickname, old_channel_id) = match old {
-            Some(old) => {
-                let nickname = old.member.unwrap().display_name().to_owned();
-                let channel_name = old.channel_id.unwrap().name(http.clone()).await.unwrap();
-                println!("user {} in {} of {} before", nickname, channel_name, guild_name);
-                (Some(nickname), old.channel_id)
-            },
-            None => (None, None)
+        let Some(guild_id) = new.guild_id else {
+            return;
         };
+        let old_channel_id = old.as_ref().and_then(|old| old.channel_id);
+        let new_channel_id = new.channel_id;
+
+        // leave right away when everyone else has left the bot's channel,
+        // before any lookup which could fail for an uncached guild or member
+        if old_channel_id.is_some() && old_channel_id != new_channel_id && is_bot_alone(&ctx, guild_id).await {
+            info!("Bot is alone in {}, leaving", guild_id);
+            drop(end_session(&ctx, guild_id).await);
+        }
 
-        let new_channel_id = if let Some(channel_id) = new.channel_id {
-            let nickname = nickname.unwrap_or(new.member.unwrap().display_name().to_owned());
-            let channel_name = channel_id.name(http.clone()).await.unwrap();
+        let guild_name = guild_id.name(&ctx.cache).unwrap_or_else(|| guild_id.to_string());
+        let nickname = old.as_ref()
+            .and_then(|old| old.member.as_ref())
+            .or(new.member.as_ref())
+            .map(|member| member.display_name().to_owned())
+            .unwrap_or_else(|| new.user_id.to_string());
 
-            println!("user {} join in {} of {} after",
-                nickname,
-                channel_name,
-                guild_name
-            );
+        if let Some(channel_id) = old_channel_id {
+            let channel_name = channel_id.name(&ctx).await.unwrap_or_else(|_| channel_id.to_string());
+            info!("user {} in {} of {} before", nickname, channel_name, guild_name);
+        }
 
-            let members = channel_id
-                .to_channel_cached(&cache)
-                .unwrap()
-                .members(cache.clone())
-                .unwrap()
-                .iter()
-                .map(|member| member.display_name().to_owned())
-                .collect::<Vec<String>>();
+        if let Some(channel_id) = new_channel_id {
+            let channel_name = channel_id.name(&ctx).await.unwrap_or_else(|_| channel_id.to_string());
+            info!("user {} join in {} of {} after", nickname, channel_name, guild_name);
+
+            let Some(members) = channel_id
+                .to_channel_cached(&ctx.cache)
+                .and_then(|channel| channel.members(&ctx.cache).ok())
+                .map(|members| members
+                    .iter()
+                    .map(|member| member.display_name().to_owned())
+                    .collect::<Vec<String>>())
+            else {
+                warn!("Channel {} of {} is not cached", channel_name, guild_name);
+                return;
+            };
 
-            let guild_queue = get_guild_queue(&ctx, guild_id).await;
-            let board_lock = guild_queue.get_board();
             let manager = songbird::get(&ctx).await.unwrap().clone();
             if let Some(handler_lock) = manager.get(guild_id) {
-                let bot_voice = handler_lock.lock().await.current_channel().unwrap();
-                if bot_voice == channel_id.into() {
-                    let mut board = board_lock.lock().await;
-                    board.add_users(members).await;
+                let bot_voice = handler_lock.lock().await.current_channel();
+                if bot_voice == Some(channel_id.into()) {
+                    if let Some(guild_queue) = find_guild_queue(&ctx, guild_id).await {
+                        guild_queue.add_players(members);
+                        guild_queue.join_smallest_team(new.user_id, nickname.clone());
+                    }
                 }
             }
-
-            Some(channel_id)
-
-        } else {
-            let nickname = nickname.unwrap_or(new.member.unwrap().display_name().to_owned());
-            let channel_name = old_channel_id.unwrap().name(http.clone()).await.unwrap();
-
-            println!("user {} leave from {} of {} after",
-                nickname,
-                channel_name,
-                guild_name
-            );
-            None
-        };
-        
-        // if let Some(old_channel_id) = old_channel_id {
-        //     println!("voice channel {} in : {:?}", 
-        //         old_channel_id.name(http.clone()).await.unwrap(),
-        //         old_channel_id
-        //             .to_channel_cached(&cache)
-        //             .unwrap()
-        //             .members(cache.clone())
-        //             .unwrap()
-        //             .iter()
-        //             .map(|member| member.display_name())
-        //             .collect::<Vec<&str>>()
-        //     );
-        // }
-
-        // if let Some(new_channel_id) = new_channel_id {
-        //     println!("voice channel {} in : {:?}", 
-        //         new_channel_id.name(http.clone()).await.unwrap(),
-        //         new_channel_id
-        //             .to_channel_cached(&cache)
-        //             .unwrap()
-        //             .members(cache.clone())
-        //             .unwrap()
-        //             .iter()
-        //             .map(|member| member.display_name())
-        //             .collect::<Vec<&str>>()
-        //     );
-        // }
+        } else if let Some(channel_id) = old_channel_id {
+            let channel_name = channel_id.name(&ctx).await.unwrap_or_else(|_| channel_id.to_string());
+            info!("user {} leave from {} of {} after", nickname, channel_name, guild_name);
+        }
+    }
+}
+
+/// Judges `msg` as an answer to the playing song, scoring and skipping it when correct.
+///
+/// Returns `None` when the message is not taken as an answer.
+pub async fn handle_answer(cache_http: impl CacheHttp, guild_queue: &GuildQueue, msg: &Message) -> Option<Verdict> {
+    if msg.guild_id.is_none() || msg.author.bot {
+        return None;
     }
-}
\ No newline at end of file
+    // looked up here, so that the session does not wait on Discord
+    let nickname = match msg.member(cache_http).await {
+        Ok(member) => member.display_name().to_owned(),
+        Err(_) => msg.author.name.clone(),
+    };
+    guild_queue.answer(msg.author.id, msg.channel_id, nickname, msg.content.clone()).await
+}
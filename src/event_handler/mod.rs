@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 pub mod event_handler;
-pub mod track_event_handler;
\ No newline at end of file
+pub mod component_handler;
\ No newline at end of file
@@ -1,3 +0,0 @@
-pub const TARGET: &str = "./target/";
-pub const TMP: &str = "/tmp/";
-pub const NO_SOUND: &str = "NO_SOUND.webm";
\ No newline at end of file
@@ -0,0 +1,137 @@
+use poise::serenity_prelude as serenity;
+use serenity::GuildId;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::{
+    command_handler::command_handler::{Data, Error},
+    utils::guild_settings,
+};
+
+/// Languages the bot speaks.
+#[derive(poise::ChoiceParameter, Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Lang {
+    #[default]
+    #[name = "한국어"]
+    Ko,
+    #[name = "English"]
+    En,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::Ko, Lang::En];
+
+    /// Language selected by the guild.
+    pub fn of(guild_id: Option<GuildId>) -> Lang {
+        guild_id.map(|guild_id| guild_settings::get(guild_id).language).unwrap_or_default()
+    }
+
+    /// Main Discord locale of this language.
+    pub fn locale(&self) -> &'static str {
+        self.discord_locales()[0]
+    }
+
+    /// Discord locales this language is registered for.
+    fn discord_locales(&self) -> &'static [&'static str] {
+        match self {
+            Lang::Ko => &["ko"],
+            Lang::En => &["en-US", "en-GB"],
+        }
+    }
+
+    fn bundle(&self) -> &'static HashMap<String, String> {
+        match self {
+            Lang::Ko => &KO,
+            Lang::En => &EN,
+        }
+    }
+}
+
+lazy_static! {
+    static ref KO: HashMap<String, String> = serde_json::from_str(include_str!("../locales/ko.json"))
+        .expect("locales/ko.json is not a valid catalog");
+    static ref EN: HashMap<String, String> = serde_json::from_str(include_str!("../locales/en.json"))
+        .expect("locales/en.json is not a valid catalog");
+}
+
+/// Returns the message for `key`, falling back to English, then Korean and then to the key itself.
+pub fn t(lang: Lang, key: &str) -> String {
+    [lang, Lang::En, Lang::default()].iter()
+        .find_map(|lang| lang.bundle().get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_owned())
+}
+
+/// Same as [`t`], replacing `{name}` placeholders with `args`.
+pub fn tr(lang: Lang, key: &str, args: &[(&str, String)]) -> String {
+    args.iter().fold(t(lang, key), |message, (name, value)| {
+        message.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+/// Returns the keys each bundle lacks compared to the others.
+///
+/// Checked by the tests, at runtime a missing key only falls back as [`t`] does.
+pub fn missing_keys() -> Vec<(Lang, String)> {
+    let mut missing = Vec::new();
+    for lang in Lang::ALL {
+        for other in Lang::ALL {
+            for key in other.bundle().keys() {
+                if !lang.bundle().contains_key(key) && !missing.contains(&(lang, key.clone())) {
+                    missing.push((lang, key.clone()));
+                }
+            }
+        }
+    }
+    missing
+}
+
+/// Fills command and parameter descriptions from the catalogs.
+///
+/// The English text becomes the default, other languages are added as Discord localizations.
+/// Keys are `command.<qualified name>.description`, `command.<qualified name>.name`
+/// and `command.<qualified name>.<parameter>`.
+pub fn localize_commands(commands: &mut [poise::Command<Data, Error>]) {
+    for command in commands {
+        localize_command(command, "");
+    }
+}
+
+fn localize_command(command: &mut poise::Command<Data, Error>, parent: &str) {
+    let path = format!("{}{}", parent, command.name);
+
+    for lang in Lang::ALL {
+        let bundle = lang.bundle();
+        if let Some(description) = bundle.get(&format!("command.{}.description", path)) {
+            if lang == Lang::En {
+                command.description = Some(description.clone());
+            }
+            for locale in lang.discord_locales() {
+                command.description_localizations.insert(locale.to_string(), description.clone());
+            }
+        }
+        if let Some(name) = bundle.get(&format!("command.{}.name", path)) {
+            for locale in lang.discord_locales() {
+                command.name_localizations.insert(locale.to_string(), name.clone());
+            }
+        }
+        for parameter in &mut command.parameters {
+            if let Some(description) = bundle.get(&format!("command.{}.{}", path, parameter.name)) {
+                if lang == Lang::En {
+                    parameter.description = Some(description.clone());
+                }
+                for locale in lang.discord_locales() {
+                    parameter.description_localizations.insert(locale.to_string(), description.clone());
+                }
+            }
+        }
+    }
+
+    let parent = format!("{}.", path);
+    for subcommand in &mut command.subcommands {
+        localize_command(subcommand, &parent);
+    }
+}
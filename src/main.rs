@@ -1,35 +1,100 @@
 use std::sync::Arc;
 
 use serenity::{all::GuildId, FutureExt};
-use songbird::{tracks::TrackQueue, typemap::{TypeMap, TypeMapKey}, SerenityInit};
+use songbird::{typemap::TypeMapKey, SerenityInit};
 use tokio::signal::ctrl_c;
 
 use poise::serenity_prelude as serenity_poise;
 use serenity::model::prelude::GatewayIntents;
 
-use reqwest::Client as HttpClient;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter, FmtSubscriber};
+use tracing::{error, info, warn};
+use tracing_subscriber::{EnvFilter, FmtSubscriber};
 use dashmap::DashMap;
 use utils::guild_queue::GuildQueue;
 
+mod admin_api;
 mod event_handler;
 mod command_handler;
+mod config;
 mod connection_handler;
+mod dashboard;
+mod error;
+mod i18n;
+mod metrics;
 mod utils;
-mod global;
+mod shutdown;
+#[cfg(test)]
+mod tests;
 
 struct GuildQueueKey;
 impl TypeMapKey for GuildQueueKey {
     type Value = Arc<DashMap<GuildId, GuildQueue>>;
 }
 
+/// Probes the media tools, exiting when one is missing unless `require_tools` is off.
+async fn check_media_tools() {
+    let report = utils::media_tools::probe().await;
+    for tool in &report.tools {
+        if let Ok(version) = &tool.version {
+            info!(tool = tool.name, command = %tool.command, version = %version, "Found media tool");
+        }
+    }
+    // not fatal, only the commands adding songs fail while the file is missing
+    if !config::get().no_sound.is_file() {
+        warn!("{} does not exist, songs cannot be added", config::get().no_sound.display());
+    } else if report.interval.is_none() {
+        warn!(
+            "Could not read the length of {} with ffprobe, the board shows no progress between songs",
+            config::get().no_sound.display(),
+        );
+    }
+    let problems = report.problems();
+    if problems.is_empty() {
+        return;
+    }
+    if config::get().require_tools {
+        for problem in &problems {
+            error!("{}", problem);
+        }
+        error!("Set `require_tools = false` to start anyway with the commands playing songs disabled");
+        std::process::exit(1);
+    }
+    for problem in &problems {
+        warn!("{}", problem);
+    }
+    warn!("Commands playing songs are disabled until /diagnose finds the media tools");
+}
+
 #[tokio::main]
 async fn main() {
-    let subscriber = FmtSubscriber::builder()
-        .with_env_filter(EnvFilter::new("info"))
-        .finish();
-    tracing::subscriber::set_global_default(subscriber)
-        .expect("Setting default subscriber failed");
+    let config = match config::load(std::env::args().skip(1), |name| std::env::var(name).ok()) {
+        Ok(config) => config,
+        Err(config::ConfigError::Help) => {
+            println!("{}", config::ConfigError::Help);
+            return;
+        },
+        Err(why) => {
+            eprintln!("{}", why);
+            std::process::exit(2);
+        }
+    };
+    let builder = FmtSubscriber::builder()
+        .with_env_filter(EnvFilter::new(&config.log_level));
+    let result = match config.log_format {
+        config::LogFormat::Text => tracing::subscriber::set_global_default(builder.finish()),
+        config::LogFormat::Json => tracing::subscriber::set_global_default(builder.json().finish()),
+    };
+    result.expect("Setting default subscriber failed");
+    config::init(config);
+
+    let missing = i18n::missing_keys();
+    if !missing.is_empty() {
+        warn!("Missing translations, falling back to English: {:?}", missing);
+    }
+    check_media_tools().await;
+    utils::guild_settings::load().await;
+    tokio::spawn(metrics::serve());
+    tokio::spawn(dashboard::serve());
 
     let token = std::env::var("DISCORD_TOKEN")
         .expect("Missing `DISCORD_TOKEN` env var, see README for more information.");
@@ -42,14 +107,24 @@ async fn main() {
         | GatewayIntents::DIRECT_MESSAGES
     ;
 
+    let framework = poise::Framework::builder()
+        .options(command_handler::command_handler::framework_options())
+        .setup(|_ctx, _ready, _framework| Box::pin(async { Ok(command_handler::command_handler::Data) }))
+        .build();
+
+    let voice = songbird::Songbird::serenity();
+    metrics::watch_voice_calls(voice.clone());
+
     let mut client: serenity::prelude::Client = serenity_poise::ClientBuilder::new(token, intents)
+        .framework(framework)
         .event_handler(event_handler::event_handler::DiscordEventHandler)
         .type_map_insert::<GuildQueueKey>(Arc::new(DashMap::new()))
-        .register_songbird()
+        .register_songbird_with(voice)
         .await
         .expect("Error creating client");
 
     let shard_manager = client.shard_manager.clone();
+    let data = client.data.clone();
     let client_future = client.start_autosharded().fuse();
 
     tokio::pin!(client_future);
@@ -60,11 +135,12 @@ async fn main() {
     tokio::select! {
         client_result = client_future => {
             if let Err(why) = client_result {
-                println!("An error occurred while running the client: {:?}", why);
+                error!("An error occurred while running the client: {:?}", why);
             }
         },
         _ = ctrlc => {
-            println!("Ctrl-C received, shutting down");
+            info!("Ctrl-C received, shutting down");
+            shutdown::shutdown(&data).await;
             shard_manager.shutdown_all().await;
         }
     };
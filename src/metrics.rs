@@ -0,0 +1,145 @@
+//! Prometheus metrics of the bot and the media pipeline.
+//!
+//! Built with the `metrics` feature, [`serve`] exposes them on `/metrics` of `metrics_addr`.
+//! Without the feature every function here does nothing.
+
+use poise::serenity_prelude as serenity;
+use serenity::GuildId;
+
+use songbird::Songbird;
+use std::{sync::Arc, time::Duration};
+
+#[cfg(feature = "metrics")]
+mod enabled {
+    use lazy_static::lazy_static;
+    use prometheus::{
+        register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+        register_int_gauge_vec, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+    };
+    use songbird::Songbird;
+    use std::sync::{Arc, OnceLock};
+    use tracing::{info, warn};
+
+    use crate::config;
+
+    pub static VOICE: OnceLock<Arc<Songbird>> = OnceLock::new();
+
+    lazy_static! {
+        pub static ref COMMANDS: IntCounterVec = register_int_counter_vec!(
+            "quiz_bot_commands_total", "Commands executed", &["command", "outcome"]
+        ).unwrap();
+        pub static ref MEDIA_SECONDS: HistogramVec = register_histogram_vec!(
+            "quiz_bot_media_seconds", "Time spent running media tools", &["tool"],
+            vec![0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]
+        ).unwrap();
+        pub static ref MEDIA_FAILURES: IntCounterVec = register_int_counter_vec!(
+            "quiz_bot_media_failures_total", "Media tool runs which failed", &["tool"]
+        ).unwrap();
+        pub static ref VOICE_CALLS: IntGauge = register_int_gauge!(
+            "quiz_bot_voice_calls", "Active voice calls"
+        ).unwrap();
+        pub static ref QUEUE_LENGTH: IntGaugeVec = register_int_gauge_vec!(
+            "quiz_bot_queue_length", "Songs waiting in the queue", &["guild"]
+        ).unwrap();
+        pub static ref CORRECT_GUESSES: IntCounter = register_int_counter!(
+            "quiz_bot_correct_guesses_total", "Correct guesses"
+        ).unwrap();
+        pub static ref MEDIA_CACHE: IntCounterVec = register_int_counter_vec!(
+            "quiz_bot_media_cache_total", "Media lookups by whether the download was cached", &["result"]
+        ).unwrap();
+    }
+
+    // counted when scraped, so that calls which ended without the bot leaving are not counted
+    async fn voice_calls() -> i64 {
+        let Some(voice) = VOICE.get() else {
+            return 0;
+        };
+        // the calls are locked after the iterator is dropped, it holds the map
+        let calls = voice.iter().map(|(_, call)| call).collect::<Vec<_>>();
+        let mut connected = 0;
+        for call in calls {
+            if call.lock().await.current_channel().is_some() {
+                connected += 1;
+            }
+        }
+        connected
+    }
+
+    async fn render() -> String {
+        VOICE_CALLS.set(voice_calls().await);
+        TextEncoder::new()
+            .encode_to_string(&prometheus::gather())
+            .unwrap_or_default()
+    }
+
+    pub async fn serve() {
+        let addr = config::get().metrics_addr;
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(why) => {
+                warn!("Failed to bind metrics endpoint to {}: {:?}", addr, why);
+                return;
+            }
+        };
+        info!("Serving metrics on http://{}/metrics", addr);
+        let app = axum::Router::new().route("/metrics", axum::routing::get(render));
+        if let Err(why) = axum::serve(listener, app).await {
+            warn!("Metrics endpoint stopped: {:?}", why);
+        }
+    }
+}
+
+/// Serves `/metrics` until the process exits.
+pub async fn serve() {
+    #[cfg(feature = "metrics")]
+    enabled::serve().await;
+}
+
+/// Counts a command by whether it succeeded or the kind of error it failed with.
+pub fn command_executed(command: &str, outcome: &str) {
+    #[cfg(feature = "metrics")]
+    enabled::COMMANDS.with_label_values(&[command, outcome]).inc();
+    #[cfg(not(feature = "metrics"))]
+    let _ = (command, outcome);
+}
+
+/// Records a run of yt-dlp or ffmpeg.
+pub fn media_run(tool: &str, elapsed: Duration, succeeded: bool) {
+    #[cfg(feature = "metrics")]
+    {
+        enabled::MEDIA_SECONDS.with_label_values(&[tool]).observe(elapsed.as_secs_f64());
+        if !succeeded {
+            enabled::MEDIA_FAILURES.with_label_values(&[tool]).inc();
+        }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (tool, elapsed, succeeded);
+}
+
+/// Counts a media lookup as a cache hit or miss.
+pub fn media_cache(hit: bool) {
+    #[cfg(feature = "metrics")]
+    enabled::MEDIA_CACHE.with_label_values(&[if hit { "hit" } else { "miss" }]).inc();
+    #[cfg(not(feature = "metrics"))]
+    let _ = hit;
+}
+
+/// Reports the calls of `voice` connected to a channel as the active voice calls.
+pub fn watch_voice_calls(voice: Arc<Songbird>) {
+    #[cfg(feature = "metrics")]
+    drop(enabled::VOICE.set(voice));
+    #[cfg(not(feature = "metrics"))]
+    let _ = voice;
+}
+
+pub fn queue_length(guild_id: GuildId, length: usize) {
+    #[cfg(feature = "metrics")]
+    enabled::QUEUE_LENGTH.with_label_values(&[&guild_id.to_string()]).set(length as i64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (guild_id, length);
+}
+
+pub fn correct_guess() {
+    #[cfg(feature = "metrics")]
+    enabled::CORRECT_GUESSES.inc();
+}
@@ -0,0 +1,106 @@
+use poise::serenity_prelude as serenity;
+use serenity::{Context, ChannelId, Channel, UserId};
+use ::serenity::prelude::{RwLock, TypeMap};
+
+use songbird::SongbirdKey;
+use tracing::{info, warn};
+
+use std::sync::Arc;
+
+use crate::{
+    config,
+    utils::{guild_queue::get_guild_queue, session_store},
+    GuildQueueKey,
+};
+
+/// Stops every guild session before the process exits.
+///
+/// Each active board is told that the bot is restarting, scores and queues are
+/// written to disk for [`restore`], all voice calls are left and the cached media is removed.
+pub async fn shutdown(data: &Arc<RwLock<TypeMap>>) {
+    let (guild_queue_map, manager) = {
+        let data_read = data.read().await;
+        (
+            data_read.get::<GuildQueueKey>().unwrap().clone(),
+            data_read.get::<SongbirdKey>().cloned(),
+        )
+    };
+
+    // DashMap guards must not be held across await
+    let guild_queues = guild_queue_map
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().clone()))
+        .collect::<Vec<_>>();
+
+    for (guild_id, guild_queue) in guild_queues {
+        let session = guild_queue.saved_session().await;
+        guild_queue.stop();
+
+        if session.channel.is_some() {
+            if let Err(why) = session_store::save(guild_id, &session).await {
+                warn!("Failed to save session of {}: {:?}", guild_id, why);
+            }
+            guild_queue.notify("board.restarting").await;
+        }
+
+        if let Some(manager) = &manager {
+            if manager.get(guild_id).is_some() {
+                drop(manager.remove(guild_id).await);
+            }
+        }
+        info!("Session of {} is closed", guild_id);
+    }
+
+    clear_cache().await;
+}
+
+/// Removes the clips cut by `ytdl_optioned` and the downloads yt-dlp did not finish.
+///
+/// Finished downloads are kept, so that resumed sessions do not fetch their songs again.
+async fn clear_cache() {
+    let Ok(mut entries) = tokio::fs::read_dir(&config::get().cache_dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let partial = name.ends_with(".part") || name.ends_with(".ytdl") || name.contains(".part-Frag");
+        if !partial && !name.contains("_cut.") {
+            continue;
+        }
+        if let Err(why) = tokio::fs::remove_file(entry.path()).await {
+            warn!("Failed to remove {:?}: {:?}", entry.path(), why);
+        }
+    }
+}
+
+/// Restores the boards, scores and hosts saved by [`shutdown`].
+///
+/// Guilds which already have a registered channel are left untouched,
+/// so it is safe to call on every `ready`.
+pub async fn restore(ctx: &Context) {
+    for (guild_id, session) in session_store::load_all().await {
+        let guild_queue = get_guild_queue(ctx, guild_id).await;
+        if guild_queue.channel().await.is_some() {
+            continue;
+        }
+
+        let Some(channel_id) = session.channel.filter(|&id| id != 0).map(ChannelId::new) else {
+            continue;
+        };
+        match channel_id.to_channel(&ctx.http).await {
+            Ok(Channel::Guild(channel)) => {
+                if let Err(why) = guild_queue.register_channel(&channel).await {
+                    warn!("Failed to restore board of {}: {:?}", guild_id, why);
+                    continue;
+                }
+                guild_queue.restore_scores(session.score);
+                // otherwise anyone would be the host until the game is resumed
+                if let Some(host) = session.host.filter(|&id| id != 0) {
+                    guild_queue.claim_host(UserId::new(host));
+                }
+                info!("Session of {} is restored", guild_id);
+            },
+            _ => warn!("Channel {} of {} is gone, session is not restored", channel_id, guild_id),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use poise::serenity_prelude as serenity;
+use serenity::{async_trait, GuildId};
+
+use parking_lot::Mutex;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::{
+    admin_api::{router, Sessions},
+    connection_handler::ConnectionErrorCode,
+    utils::guild_queue::GuildQueue,
+};
+use super::{mock_discord::GUILD_ID, session, Session};
+
+const TOKEN: &str = "secret";
+
+/// Sessions of the tests, ending one only forgets it.
+#[derive(Clone, Default)]
+struct FakeSessions {
+    guild_queues: Arc<Mutex<Vec<(GuildId, GuildQueue)>>>,
+    ended: Arc<Mutex<Vec<GuildId>>>,
+}
+
+#[async_trait]
+impl Sessions for FakeSessions {
+    async fn guild_queues(&self) -> Vec<(GuildId, GuildQueue)> {
+        self.guild_queues.lock().clone()
+    }
+
+    async fn end_session(&self, guild_id: GuildId) -> Result<(), ConnectionErrorCode> {
+        self.guild_queues.lock().retain(|(id, _)| *id != guild_id);
+        self.ended.lock().push(guild_id);
+        Ok(())
+    }
+}
+
+/// Serves the API on `sessions` at an ephemeral port, returning its base URL.
+async fn serve(sessions: FakeSessions) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router(sessions, Some(TOKEN))).await.unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+/// The API on a guild with `songs` queued.
+async fn api(songs: &[(&str, &str)]) -> (Session, FakeSessions, String) {
+    let session = session(songs).await;
+    let sessions = FakeSessions::default();
+    sessions.guild_queues.lock().push((GuildId::new(GUILD_ID), session.guild_queue.clone()));
+    let base = serve(sessions.clone()).await;
+    (session, sessions, base)
+}
+
+#[tokio::test]
+async fn requests_without_the_token_are_rejected() {
+    let (_session, _, base) = api(&[]).await;
+    let client = Client::new();
+
+    let missing = client.get(format!("{}/guilds", base)).send().await.unwrap();
+    assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+    let wrong = client.post(format!("{}/guilds/{}/stop", base, GUILD_ID))
+        .bearer_auth("guess")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn guilds_are_listed_with_their_queue() {
+    let (_session, _, base) = api(&[("first", "one")]).await;
+
+    let response = Client::new().get(format!("{}/guilds", base)).bearer_auth(TOKEN).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let guilds: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(guilds.as_array().map(Vec::len), Some(1));
+    assert_eq!(guilds[0]["guild_id"], GUILD_ID);
+    assert_eq!(guilds[0]["now_playing"], "title of first");
+}
+
+#[tokio::test]
+async fn stop_ends_the_session() {
+    let (_session, sessions, base) = api(&[("first", "one")]).await;
+    let client = Client::new();
+
+    let response = client.post(format!("{}/guilds/{}/stop", base, GUILD_ID)).bearer_auth(TOKEN).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(*sessions.ended.lock(), [GuildId::new(GUILD_ID)]);
+
+    let response = client.get(format!("{}/guilds/{}", base, GUILD_ID)).bearer_auth(TOKEN).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,34 @@
+use poise::serenity_prelude as serenity;
+use serenity::UserId;
+
+use crate::utils::choice_round::{ChoiceRound, Pick, DECOYS};
+
+fn answers(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn rounds_show_the_answer_among_decoys() {
+    let round = ChoiceRound::new("answer".to_owned(), &answers(&["a", "b", "c", "d", "answer", "a"])).unwrap();
+    let options = (0..).map_while(|index| round.option(index)).collect::<Vec<_>>();
+
+    assert_eq!(options.len(), DECOYS + 1);
+    assert_eq!(options.iter().filter(|option| **option == "answer").count(), 1);
+    assert_eq!(round.answer(), "answer");
+}
+
+#[test]
+fn rounds_of_other_games_do_not_share_menus() {
+    let pool = answers(&["decoy"]);
+    let mut first = ChoiceRound::new("answer".to_owned(), &pool).unwrap();
+    let second = ChoiceRound::new("answer".to_owned(), &pool).unwrap();
+
+    assert_ne!(first.custom_id(), second.custom_id());
+    assert_eq!(first.pick(&second.custom_id(), UserId::new(1), 0), Pick::Closed);
+}
+
+#[test]
+fn no_round_without_decoys() {
+    assert!(ChoiceRound::new("answer".to_owned(), &[]).is_none());
+    assert!(ChoiceRound::new("answer".to_owned(), &answers(&["answer"])).is_none());
+}
@@ -0,0 +1,66 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+use crate::config::{load, ConfigError, Config, Layer, LogFormat};
+
+/// A directory of its own under the temp dir, holding the silent track and a config file.
+fn workspace(name: &str, file: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("quiz_bot_config_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("NO_SOUND.webm"), []).unwrap();
+    let file = format!("no_sound = {:?}\ncache_dir = {:?}\n{}", dir.join("NO_SOUND.webm"), dir.join("cache"), file);
+    fs::write(dir.join("config.toml"), file).unwrap();
+    dir
+}
+
+fn run(dir: &Path, flags: &[&str], env: &[(&str, &str)]) -> Result<Config, ConfigError> {
+    let env = env.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect::<HashMap<_, _>>();
+    let args = ["--config".to_owned(), dir.join("config.toml").to_string_lossy().into_owned()]
+        .into_iter()
+        .chain(flags.iter().map(|flag| flag.to_string()));
+    load(args, |name| env.get(name).cloned())
+}
+
+#[test]
+fn later_layers_win() {
+    let dir = workspace("layers", "max_downloads = 2\nlog_format = \"json\"\n");
+    let config = run(&dir, &["--max-downloads", "5", "--log-level=debug"], &[
+        ("QUIZ_BOT_MAX_DOWNLOADS", "3"),
+        ("QUIZ_BOT_METRICS_ADDR", "127.0.0.1:2000"),
+    ]).unwrap();
+
+    assert_eq!(config.max_downloads, 5);
+    assert_eq!(config.log_level, "debug");
+    assert_eq!(config.log_format, LogFormat::Json);
+    assert_eq!(config.metrics_addr.port(), 2000);
+    assert_eq!(config.data_dir, Config::default().data_dir);
+    assert!(dir.join("cache").is_dir());
+}
+
+#[test]
+fn bad_values_name_their_source() {
+    let dir = workspace("values", "");
+    match run(&dir, &["--max-downloads", "many"], &[]) {
+        Err(ConfigError::Value { layer: Layer::Flag(flag), key, .. }) => {
+            assert_eq!(flag, "--max-downloads");
+            assert_eq!(key, "max_downloads");
+        },
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(matches!(
+        run(&dir, &[], &[("QUIZ_BOT_LOG_FORMAT", "xml")]),
+        Err(ConfigError::Value { layer: Layer::Env(_), .. })
+    ));
+    assert!(matches!(run(&dir, &["--volume", "3"], &[]), Err(ConfigError::Usage(_))));
+}
+
+#[test]
+fn unusable_values_stop_startup() {
+    let dir = workspace("invalid", "dashboard_addr = \"0.0.0.0:9900\"\n");
+    assert!(matches!(run(&dir, &[], &[]), Err(ConfigError::Invalid { key: "dashboard_addr", .. })));
+
+    let dir = workspace("metrics", "metrics_addr = \"0.0.0.0:9898\"\n");
+    assert!(matches!(run(&dir, &[], &[]), Err(ConfigError::Invalid { key: "metrics_addr", .. })));
+
+    let dir = workspace("unknown", "cache = \"./cache\"\n");
+    assert!(matches!(run(&dir, &[], &[]), Err(ConfigError::File(..))));
+}
@@ -0,0 +1,54 @@
+use poise::serenity_prelude::async_trait;
+use songbird::input::{AudioStreamError, Input};
+
+use crate::utils::youtube_dl::{MediaBackend, MetaData, TrackSource};
+
+/// Media backend which never runs yt-dlp or ffmpeg.
+///
+/// Every source becomes a second of silence titled after its url, and so does the interval.
+pub struct FakeMedia;
+
+#[async_trait]
+impl MediaBackend for FakeMedia {
+    async fn prepare(&self, source: &TrackSource) -> Result<(Input, MetaData), AudioStreamError> {
+        let meta = MetaData {
+            duration: Some(if source.duration > 0 { source.duration } else { 1 }),
+            title: Some(format!("title of {}", source.url)),
+            keyword: source.keyword.clone(),
+            webpage_url: None,
+            artist: None,
+            album: None,
+            thumbnail: None,
+            year: None,
+            source: Some(source.clone()),
+        };
+        Ok((silence().into(), meta))
+    }
+
+    async fn interval(&self) -> Result<(Input, MetaData), AudioStreamError> {
+        Ok((silence().into(), MetaData::interval(Some(1))))
+    }
+}
+
+/// A second of 8kHz mono silence as a wav file.
+fn silence() -> Vec<u8> {
+    const RATE: u32 = 8000;
+    let data_len = RATE * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, mono
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&RATE.to_le_bytes());
+    wav.extend_from_slice(&(RATE * 2).to_le_bytes());
+    // block align, bits per sample
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.resize(44 + data_len as usize, 0);
+    wav
+}
@@ -0,0 +1,7 @@
+use crate::i18n::missing_keys;
+
+#[test]
+fn every_language_has_every_key() {
+    let missing = missing_keys();
+    assert!(missing.is_empty(), "missing translations: {:?}", missing);
+}
@@ -0,0 +1,250 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use poise::serenity_prelude as serenity;
+use serenity::{
+    prelude::{RwLock, TypeMap},
+    Cache, CommandInteraction, Context, GatewayIntents, GuildChannel, Http, HttpBuilder, Message, Shard, ShardId,
+    ShardInfo, ShardManager, ShardManagerOptions, ShardMessenger, ShardRunner, ShardRunnerOptions,
+};
+
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+pub const GUILD_ID: u64 = 10;
+pub const CHANNEL_ID: u64 = 20;
+pub const BOT_ID: u64 = 30;
+pub const TIMESTAMP: &str = "2024-01-01T00:00:00.000000+00:00";
+pub const INTERACTION_TOKEN: &str = "interaction-token";
+
+/// A request the bot made to the Discord REST API.
+#[derive(Clone, Debug)]
+pub struct Recorded {
+    pub method: Method,
+    // without the `/api/v10` prefix
+    pub path: String,
+    pub body: Value,
+}
+
+#[derive(Default)]
+struct Inner {
+    requests: Mutex<Vec<Recorded>>,
+    next_id: AtomicU64,
+}
+
+/// Local stand-in for the Discord REST API.
+///
+/// Threads and messages are created and edited as asked and every request is recorded.
+/// Anything else is answered with 404, which the bot already treats as "not found".
+#[derive(Clone)]
+pub struct MockDiscord {
+    inner: Arc<Inner>,
+    url: String,
+}
+
+impl MockDiscord {
+    pub async fn start() -> Self {
+        let inner = Arc::new(Inner { next_id: AtomicU64::new(1000), ..Default::default() });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().fallback(respond).with_state(inner.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Self { inner, url }
+    }
+
+    /// Client which sends every request to this mock.
+    pub fn http(&self) -> Arc<Http> {
+        Arc::new(HttpBuilder::new("mock-token")
+            .proxy(&self.url)
+            .ratelimiter_disabled(true)
+            // interaction responses are sent as the application
+            .application_id(BOT_ID.into())
+            .build())
+    }
+
+    /// Context of a shard, as the event handlers and the framework get it, with an empty cache and data.
+    ///
+    /// The shard is connected to a stand-in gateway but its runner never runs, so no events come in.
+    /// The returned manager is what the framework is given.
+    pub async fn context(&self) -> (Context, Arc<ShardManager>) {
+        let gateway = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = Arc::new(tokio::sync::Mutex::new(format!("ws://{}", gateway.local_addr().unwrap())));
+        tokio::spawn(async move {
+            let (stream, _) = gateway.accept().await.unwrap();
+            // held open until the test ends, nothing is ever sent over it
+            let _socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let http = self.http();
+        let cache = Arc::new(Cache::new());
+        let data = Arc::new(RwLock::new(TypeMap::new()));
+        let (manager, _) = ShardManager::new(ShardManagerOptions {
+            data: data.clone(),
+            event_handlers: Vec::new(),
+            raw_event_handlers: Vec::new(),
+            framework: Default::default(),
+            shard_index: 0,
+            shard_init: 1,
+            shard_total: 1,
+            voice_manager: None,
+            ws_url: ws_url.clone(),
+            cache: cache.clone(),
+            http: http.clone(),
+            intents: GatewayIntents::empty(),
+            presence: None,
+        });
+        let shard_info = ShardInfo { id: ShardId(0), total: 1 };
+        let shard = Shard::new(ws_url, "mock-token", shard_info, GatewayIntents::empty(), None).await.unwrap();
+        let runner = ShardRunner::new(ShardRunnerOptions {
+            data: data.clone(),
+            event_handlers: Vec::new(),
+            raw_event_handlers: Vec::new(),
+            framework: None,
+            manager: manager.clone(),
+            shard,
+            voice_manager: None,
+            cache: cache.clone(),
+            http: http.clone(),
+        });
+        let ctx = Context { data, shard: ShardMessenger::new(&runner), shard_id: ShardId(0), http, cache };
+        (ctx, manager)
+    }
+
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.inner.requests.lock().clone()
+    }
+
+    /// Waits until a recorded request satisfies `predicate`, as the board edits in the background.
+    pub async fn wait_for(&self, timeout: Duration, predicate: impl Fn(&Recorded) -> bool) -> Option<Recorded> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Some(found) = self.requests().into_iter().find(|request| predicate(request)) {
+                return Some(found);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return None;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+pub fn user(id: u64, name: &str) -> Value {
+    json!({ "id": id.to_string(), "username": name, "discriminator": "0", "avatar": null })
+}
+
+pub fn text_channel() -> GuildChannel {
+    serde_json::from_value(json!({
+        "id": CHANNEL_ID.to_string(),
+        "guild_id": GUILD_ID.to_string(),
+        "type": 0,
+        "name": "quiz",
+    }))
+    .unwrap()
+}
+
+/// A message from a member, as the gateway delivers it.
+pub fn member_message(channel_id: u64, author: Value, content: &str) -> Message {
+    serde_json::from_value(message_json(1, channel_id, author, content, Vec::new())).unwrap()
+}
+
+/// A slash command from a member, `options` holding the subcommand and arguments.
+pub fn command_interaction(author: Value, name: &str, options: Value) -> CommandInteraction {
+    serde_json::from_value(json!({
+        "id": "1",
+        "application_id": BOT_ID.to_string(),
+        "type": 2,
+        "token": INTERACTION_TOKEN,
+        "guild_id": GUILD_ID.to_string(),
+        "channel_id": CHANNEL_ID.to_string(),
+        "member": {
+            "user": author,
+            "roles": [],
+            "joined_at": TIMESTAMP,
+            "deaf": false,
+            "mute": false,
+            "flags": 0,
+            "permissions": "0",
+        },
+        "data": { "id": "2", "name": name, "type": 1, "options": options },
+        "locale": "ko",
+        "version": 1,
+        "entitlements": [],
+    }))
+    .unwrap()
+}
+
+fn message_json(id: u64, channel_id: u64, author: Value, content: &str, embeds: Vec<Value>) -> Value {
+    json!({
+        "id": id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "guild_id": GUILD_ID.to_string(),
+        "author": author,
+        "content": content,
+        "timestamp": TIMESTAMP,
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": embeds,
+        "pinned": false,
+        "type": 0,
+    })
+}
+
+async fn respond(State(inner): State<Arc<Inner>>, method: Method, uri: Uri, body: Bytes) -> Response {
+    let path = uri.path().trim_start_matches("/api/v10").to_owned();
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    inner.requests.lock().push(Recorded { method: method.clone(), path: path.clone(), body: body.clone() });
+
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+    let id = || inner.next_id.fetch_add(1, Ordering::SeqCst);
+    let embeds = || body.get("embeds").and_then(Value::as_array).cloned().unwrap_or_default();
+    match (method, segments.as_slice()) {
+        (Method::POST, ["channels", parent, "threads"]) => Json(json!({
+            "id": id().to_string(),
+            "guild_id": GUILD_ID.to_string(),
+            "parent_id": parent,
+            "owner_id": BOT_ID.to_string(),
+            "type": 11,
+            "name": body.get("name").cloned().unwrap_or(json!("thread")),
+        }))
+        .into_response(),
+        (Method::POST, ["channels", channel, "messages"]) => {
+            let channel = channel.parse().unwrap_or_default();
+            Json(message_json(id(), channel, user(BOT_ID, "bot"), "", embeds())).into_response()
+        },
+        (Method::PATCH, ["channels", channel, "messages", message]) => {
+            let (channel, message) = (channel.parse().unwrap_or_default(), message.parse().unwrap_or_default());
+            Json(message_json(message, channel, user(BOT_ID, "bot"), "", embeds())).into_response()
+        },
+        (Method::GET, ["channels", _, "messages"]) => Json(json!([])).into_response(),
+        (Method::POST, ["interactions", _, _, "callback"]) => StatusCode::NO_CONTENT.into_response(),
+        // the original response once deferred, and the follow-ups
+        (Method::PATCH | Method::POST, ["webhooks", ..]) => {
+            Json(message_json(id(), CHANNEL_ID, user(BOT_ID, "bot"), "", embeds())).into_response()
+        },
+        (Method::PATCH, ["channels", channel]) => Json(json!({
+            "id": channel,
+            "guild_id": GUILD_ID.to_string(),
+            "type": 11,
+            "name": "Board",
+        }))
+        .into_response(),
+        _ => (StatusCode::NOT_FOUND, Json(json!({ "message": "Unknown", "code": 0 }))).into_response(),
+    }
+}
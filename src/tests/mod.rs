@@ -0,0 +1,340 @@
+//! End-to-end tests against a mock of the Discord REST API and a fake media backend.
+//!
+//! Slash commands go through the poise framework with the options of the bot, and chat messages
+//! through its event handler, both with a shard connected to a stand-in gateway.
+//!
+//! Nothing here needs a token, yt-dlp, ffmpeg or the network, and nothing is written outside
+//! of the temp dir. The voice driver never runs,
+//! so tracks do not advance on their own: the songbird events are sent to the session as commands.
+
+mod admin_api;
+mod choice_round;
+mod config;
+mod fake_media;
+mod i18n;
+mod mock_discord;
+
+use poise::serenity_prelude as serenity;
+use serenity::{CommandInteraction, EventHandler, FullEvent, GuildId, Interaction, ShardManager, UserId};
+
+use axum::http::Method;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use quiz_core::answer::Verdict;
+use serde_json::json;
+use songbird::{tracks::Track, Driver};
+use std::{sync::{Arc, Once}, time::Duration};
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+    command_handler::command_handler::{framework_options, Data},
+    config::Config,
+    error::BotError,
+    event_handler::event_handler::{handle_answer, DiscordEventHandler},
+    i18n::{t, Lang},
+    utils::{
+        guild_queue::{Command, GuildQueue},
+        session_store::SavedSession,
+        youtube_dl::{prepare_interval, prepare_source, set_backend, TrackSource},
+    },
+    GuildQueueKey,
+};
+use fake_media::FakeMedia;
+use mock_discord::*;
+
+const PLAYER_ID: u64 = 40;
+// the renderer edits at most every two seconds
+const EDIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    // the drivers spawn their mixers on a runtime which is never driven, so tracks do not play;
+    // it is shared as songbird keeps its scheduler on the runtime of the first driver
+    static ref VOICE_RUNTIME: Runtime = Builder::new_current_thread().build().unwrap();
+}
+
+/// Keeps the data and cache dirs of the sessions in the temp dir.
+///
+/// Called before anything reads the configuration, which would fix the defaults.
+fn init_config() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("quiz_bot_tests_{}", std::process::id()));
+        crate::config::init(Config {
+            cache_dir: dir.join("cache"),
+            data_dir: dir.join("data"),
+            ..Config::default()
+        });
+    });
+}
+
+struct Session {
+    discord: MockDiscord,
+    guild_queue: GuildQueue,
+    // keeps the queued tracks alive
+    driver: Driver,
+}
+
+/// A guild with its board in `CHANNEL_ID`, the player in the score and `songs` queued.
+async fn session(songs: &[(&str, &str)]) -> Session {
+    init_config();
+    set_backend(Arc::new(FakeMedia));
+    let discord = MockDiscord::start().await;
+    let guild_queue = GuildQueue::new(GuildId::new(GUILD_ID), discord.http(), None);
+    guild_queue.register_channel(&text_channel()).await.unwrap();
+    guild_queue.add_players(vec!["player".to_owned()]);
+
+    let mut driver = {
+        let _runtime = VOICE_RUNTIME.enter();
+        Driver::new(songbird::Config::default())
+    };
+    for (url, keyword) in songs {
+        let source = TrackSource {
+            url: url.to_string(),
+            start: 0,
+            duration: 0,
+            keyword: Some(keyword.to_string()),
+        };
+        let (input, meta) = prepare_source(&source).await.unwrap();
+        guild_queue.add_source(input, meta, &mut driver, false).await.unwrap();
+    }
+    // the interval in front ends to start the round of the first song
+    if let Some((interval, _)) = guild_queue.current().await {
+        guild_queue.send(Command::TrackEnded { track: interval, errored: false });
+    }
+    Session { discord, guild_queue, driver }
+}
+
+async fn score_of(guild_queue: &GuildQueue, member: &str) -> Option<i32> {
+    guild_queue.scores().await.get(member).copied()
+}
+
+#[tokio::test]
+async fn board_is_posted_in_a_thread() {
+    let Session { discord, .. } = session(&[]).await;
+
+    let requests = discord.requests();
+    assert!(requests.iter().any(|request| {
+        request.method == Method::POST && request.path == format!("/channels/{}/threads", CHANNEL_ID)
+    }));
+    let titles = requests.iter()
+        .filter(|request| request.method == Method::POST && request.path.ends_with("/messages"))
+        .filter_map(|request| request.body["embeds"][0]["title"].as_str().map(str::to_owned))
+        .collect::<Vec<_>>();
+    assert_eq!(titles, [t(Lang::Ko, "board.score"), t(Lang::Ko, "board.now_playing")]);
+}
+
+#[tokio::test]
+async fn queue_holds_prepared_songs() {
+    let Session { guild_queue, .. } = session(&[("first", "one"), ("second", "two")]).await;
+
+    let titles = guild_queue.current_queue().await
+        .into_iter()
+        .filter_map(|(_, meta)| meta.source.and(meta.title))
+        .collect::<Vec<_>>();
+    assert_eq!(titles, ["title of first", "title of second"]);
+    assert_eq!(guild_queue.current().await.unwrap().1.keyword.as_deref(), Some("one"));
+}
+
+#[tokio::test]
+async fn correct_guess_increments_score_embed() {
+    let Session { discord, guild_queue, .. } = session(&[("first", "정답"), ("second", "다음")]).await;
+
+    let msg = member_message(CHANNEL_ID, user(PLAYER_ID, "player"), "정답");
+    assert_eq!(handle_answer(discord.http(), &guild_queue, &msg).await, Some(Verdict::Correct));
+    assert_eq!(score_of(&guild_queue, "player").await, Some(1));
+    // the song ends right away, the interval before the next one plays
+    assert!(guild_queue.current().await.unwrap().1.source.is_none());
+
+    let edit = discord.wait_for(EDIT_TIMEOUT, |request| {
+        request.method == Method::PATCH
+            && request.body["embeds"][0]["title"] == t(Lang::Ko, "board.score")
+            && request.body["embeds"][0]["description"].as_str().is_some_and(|text| text.contains("player: 1"))
+    }).await;
+    assert!(edit.is_some(), "score embed was not edited: {:#?}", discord.requests());
+}
+
+#[tokio::test]
+async fn wrong_and_close_guesses_do_not_score() {
+    let Session { discord, guild_queue, .. } = session(&[("first", "Answer")]).await;
+
+    let wrong = member_message(CHANNEL_ID, user(PLAYER_ID, "player"), "something else");
+    assert_eq!(handle_answer(discord.http(), &guild_queue, &wrong).await, Some(Verdict::Wrong));
+    let close = member_message(CHANNEL_ID, user(PLAYER_ID, "player"), "answer");
+    assert_eq!(handle_answer(discord.http(), &guild_queue, &close).await, Some(Verdict::Close));
+
+    assert_eq!(score_of(&guild_queue, "player").await, Some(0));
+    assert_eq!(guild_queue.current().await.unwrap().1.keyword.as_deref(), Some("Answer"));
+}
+
+#[tokio::test]
+async fn messages_elsewhere_are_not_answers() {
+    let Session { discord, guild_queue, .. } = session(&[("first", "정답")]).await;
+
+    let other_channel = member_message(CHANNEL_ID + 1, user(PLAYER_ID, "player"), "정답");
+    assert_eq!(handle_answer(discord.http(), &guild_queue, &other_channel).await, None);
+
+    let mut bot = user(BOT_ID, "bot");
+    bot["bot"] = true.into();
+    let from_bot = member_message(CHANNEL_ID, bot, "정답");
+    assert_eq!(handle_answer(discord.http(), &guild_queue, &from_bot).await, None);
+
+    assert_eq!(score_of(&guild_queue, "player").await, Some(0));
+}
+
+/// Whether a round summary was posted to the board thread.
+fn summary_posted(discord: &MockDiscord) -> bool {
+    discord.requests().iter().any(|request| {
+        request.method == Method::POST && request.body["embeds"][1]["title"] == t(Lang::Ko, "summary.title")
+    })
+}
+
+#[tokio::test]
+async fn ended_song_is_summed_up() {
+    let Session { discord, guild_queue, .. } = session(&[("first", "정답"), ("second", "다음")]).await;
+
+    let wrong = member_message(CHANNEL_ID, user(PLAYER_ID, "player"), "something else");
+    assert_eq!(handle_answer(discord.http(), &guild_queue, &wrong).await, Some(Verdict::Wrong));
+    let (song, _) = guild_queue.current().await.unwrap();
+    guild_queue.send(Command::TrackEnded { track: song, errored: false });
+
+    // the interval in front of the next song is playing
+    assert!(guild_queue.current().await.unwrap().1.source.is_none());
+    assert!(summary_posted(&discord), "round was not summed up: {:#?}", discord.requests());
+}
+
+#[tokio::test]
+async fn failed_song_is_skipped_without_a_summary() {
+    let Session { discord, guild_queue, .. } = session(&[("first", "정답"), ("second", "다음")]).await;
+
+    // songbird reports the error before the end of the track
+    let (song, _) = guild_queue.current().await.unwrap();
+    guild_queue.send(Command::TrackFailed { track: song.clone(), error: "broken".to_owned(), retry: false });
+    guild_queue.send(Command::TrackEnded { track: song, errored: true });
+
+    assert!(guild_queue.current().await.unwrap().1.source.is_none());
+    assert!(discord.requests().iter().any(|request| {
+        request.method == Method::POST && request.body["content"] == t(Lang::Ko, "track.failed_skip")
+    }));
+    assert!(!summary_posted(&discord));
+    assert_eq!(score_of(&guild_queue, "player").await, Some(0));
+}
+
+#[tokio::test]
+async fn retried_song_keeps_intervals_between_songs() {
+    let Session { discord, guild_queue, mut driver } = session(&[("first", "정답"), ("second", "다음")]).await;
+
+    let (song, meta) = guild_queue.current().await.unwrap();
+    guild_queue.send(Command::TrackEnded { track: song, errored: true });
+    // prepared again while the interval in front of the next song plays
+    let (interval, interval_meta) = prepare_interval().await.unwrap();
+    let (input, _) = prepare_source(meta.source.as_ref().unwrap()).await.unwrap();
+    guild_queue.send(Command::Requeue {
+        interval: driver.play(Track::from(interval).pause()),
+        interval_meta: Box::new(interval_meta),
+        track: driver.play(Track::from(input).pause()),
+        meta: Box::new(meta),
+    });
+
+    let keywords = guild_queue.current_queue().await
+        .into_iter()
+        .map(|(_, meta)| meta.keyword)
+        .collect::<Vec<_>>();
+    assert_eq!(keywords, [None, Some("정답".to_owned()), None, Some("다음".to_owned())]);
+
+    // the session plays the retried song once the interval ends
+    let (interval, _) = guild_queue.current().await.unwrap();
+    guild_queue.send(Command::TrackEnded { track: interval, errored: false });
+    let msg = member_message(CHANNEL_ID, user(PLAYER_ID, "player"), "정답");
+    assert_eq!(handle_answer(discord.http(), &guild_queue, &msg).await, Some(Verdict::Correct));
+}
+
+#[tokio::test]
+async fn saved_session_keeps_the_host() {
+    let Session { guild_queue, .. } = session(&[("first", "정답")]).await;
+
+    guild_queue.claim_host(UserId::new(PLAYER_ID));
+    assert_eq!(guild_queue.saved_session().await.host, Some(PLAYER_ID));
+
+    // saved before the host was kept
+    let old: SavedSession = serde_json::from_value(json!({ "channel": null, "score": {}, "tracks": [] })).unwrap();
+    assert_eq!(old.host, None);
+}
+
+/// Context of the bot, with `guild_queue` as the session of its guild if given.
+async fn bot_context(discord: &MockDiscord, guild_queue: Option<&GuildQueue>) -> (serenity::Context, Arc<ShardManager>) {
+    let (ctx, manager) = discord.context().await;
+    let sessions = Arc::new(DashMap::new());
+    if let Some(guild_queue) = guild_queue {
+        sessions.insert(GuildId::new(GUILD_ID), guild_queue.clone());
+    }
+    ctx.data.write().await.insert::<GuildQueueKey>(sessions);
+    (ctx, manager)
+}
+
+/// Runs `interaction` through the framework with the options of the bot, hooks and checks included.
+async fn dispatch(ctx: &serenity::Context, manager: &Arc<ShardManager>, interaction: CommandInteraction) {
+    let mut options = framework_options();
+    poise::set_qualified_names(&mut options.commands);
+    let framework = poise::FrameworkContext {
+        bot_id: UserId::new(BOT_ID),
+        options: &options,
+        user_data: &Data,
+        shard_manager: manager,
+    };
+    let event = FullEvent::InteractionCreate { interaction: Interaction::Command(interaction) };
+    poise::dispatch_event(framework, ctx, event).await;
+}
+
+fn subcommand(name: &str) -> serde_json::Value {
+    json!([{ "name": name, "type": 1, "options": [] }])
+}
+
+#[tokio::test]
+async fn slash_command_is_deferred_and_answered() {
+    let Session { discord, guild_queue, .. } = session(&[("first", "정답"), ("second", "다음")]).await;
+    let (ctx, manager) = bot_context(&discord, Some(&guild_queue)).await;
+
+    // passes the DJ check, as the guild has no DJ role
+    dispatch(&ctx, &manager, command_interaction(user(PLAYER_ID, "player"), "queue", subcommand("skip"))).await;
+
+    let requests = discord.requests();
+    assert!(requests.iter().any(|request| {
+        request.method == Method::POST
+            && request.path == format!("/interactions/1/{}/callback", INTERACTION_TOKEN)
+            && request.body["type"] == 5
+    }), "command was not deferred: {:#?}", requests);
+    assert!(requests.iter().any(|request| {
+        request.method == Method::PATCH
+            && request.path == format!("/webhooks/{}/{}/messages/@original", BOT_ID, INTERACTION_TOKEN)
+            && request.body["content"] == t(Lang::Ko, "queue.skipped")
+    }), "deferred response was not edited: {:#?}", requests);
+}
+
+#[tokio::test]
+async fn failed_command_replies_with_an_error_embed() {
+    init_config();
+    let discord = MockDiscord::start().await;
+    let (ctx, manager) = bot_context(&discord, None).await;
+
+    dispatch(&ctx, &manager, command_interaction(user(PLAYER_ID, "player"), "queue", subcommand("list"))).await;
+
+    // the response was deferred before the command failed, the error is a follow-up
+    let no_session = BotError::Unavailable("error.no_session".to_owned());
+    let requests = discord.requests();
+    assert!(requests.iter().any(|request| {
+        request.method == Method::POST
+            && request.path == format!("/webhooks/{}/{}", BOT_ID, INTERACTION_TOKEN)
+            && request.body["embeds"][0]["description"] == no_session.user_message(Lang::Ko)
+    }), "error was not replied: {:#?}", requests);
+}
+
+#[tokio::test]
+async fn chat_message_is_judged_by_the_event_handler() {
+    let Session { discord, guild_queue, .. } = session(&[("first", "정답"), ("second", "다음")]).await;
+    let (ctx, _manager) = bot_context(&discord, Some(&guild_queue)).await;
+
+    DiscordEventHandler.message(ctx, member_message(CHANNEL_ID, user(PLAYER_ID, "player"), "정답")).await;
+
+    assert_eq!(score_of(&guild_queue, "player").await, Some(1));
+    assert!(guild_queue.current().await.unwrap().1.source.is_none());
+}
@@ -2,126 +2,513 @@ use chrono::Utc;
 use poise::serenity_prelude as serenity;
 use ::serenity::all::EditMessage;
 use serenity::{
-    Http, ChannelType, Message, GuildChannel, GuildId, CreateThread, CreateMessage, CreateEmbed, CacheHttp
+    Http, ChannelType, Message, GuildChannel, GuildId, CreateThread, CreateMessage, CreateEmbed,
+    EditThread, GetMessages, UserId, CreateActionRow, CreateButton, ButtonStyle
 };
 
-use std::mem;
+use tracing::warn;
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::HashSet;
 
-use super::youtube_dl::MetaData;
+use super::{
+    board_renderer::{BoardRenderer, StatusView},
+    choice_round::{self, ChoiceRound, Pick},
+    guild_settings::{self, AnswerMode},
+    quiz_store,
+    youtube_dl::MetaData,
+};
+use crate::{i18n::{t, tr, Lang}, metrics};
+use quiz_core::{answer::Verdict, game::Round, guess::GuessLog};
+
+const BOARD_NAME: &str = "Board";
+
+/// Buttons on the status message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardControl {
+    PauseResume,
+    Skip,
+    VoteSkip,
+    Hint,
+    Stop,
+}
+
+impl BoardControl {
+    const PREFIX: &'static str = "board:";
+    pub const ALL: [BoardControl; 5] = [
+        BoardControl::PauseResume,
+        BoardControl::Skip,
+        BoardControl::VoteSkip,
+        BoardControl::Hint,
+        BoardControl::Stop,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            BoardControl::PauseResume => "pause",
+            BoardControl::Skip => "skip",
+            BoardControl::VoteSkip => "vote_skip",
+            BoardControl::Hint => "hint",
+            BoardControl::Stop => "stop",
+        }
+    }
+
+    pub fn custom_id(&self) -> String {
+        format!("{}{}", Self::PREFIX, self.name())
+    }
+
+    pub fn from_custom_id(custom_id: &str) -> Option<Self> {
+        let name = custom_id.strip_prefix(Self::PREFIX)?;
+        Self::ALL.into_iter().find(|control| control.name() == name)
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Board {
+    guild_id: GuildId,
     thread: Option<GuildChannel>,
     score_message: Option<Message>,
     status_message: Option<Message>,
-    score: HashMap<String, i32>,
+    // owns the edits of the score and status messages
+    renderer: Option<BoardRenderer>,
+    // scores and teams of the game, as last shown
+    score_text: String,
+    // song shown on the status message and when it ends, as a unix timestamp
+    now_playing: Option<(MetaData, i64)>,
+    // seconds left on the song when it was paused
+    paused_remaining: Option<i64>,
+    // songs waiting after the playing one
+    queue_len: usize,
+    skip_votes: HashSet<UserId>,
+    // multiple-choice round of the playing song and the message carrying its menu
+    round: Option<ChoiceRound>,
+    round_message: Option<Message>,
+    // answers of songs played in this game, used as decoys
+    played_answers: Vec<String>,
     http: Arc<Http>
 }
 
 impl Board {
-    pub fn new(http: Arc<Http>) -> Self {
+    pub fn new(guild_id: GuildId, http: Arc<Http>) -> Self {
         Self {
+            guild_id,
             thread: None,
             score_message: None,
             status_message: None,
-            score: HashMap::new(),
+            renderer: None,
+            score_text: String::new(),
+            now_playing: None,
+            paused_remaining: None,
+            queue_len: 0,
+            skip_votes: HashSet::new(),
+            round: None,
+            round_message: None,
+            played_answers: Vec::new(),
             http
         }
     }
 
     pub async fn delete(&mut self) {
+        self.renderer = None;
         if let Some(thread) = self.thread.take() {
-            thread.delete(&self.http).await.unwrap();
+            if let Err(why) = thread.delete(&self.http).await {
+                warn!(error = ?why, "Failed to delete board thread");
+            }
             self.score_message = None;
             self.status_message = None;
         }
     }
 
-    pub async fn set(&mut self, channel: &GuildChannel) {
+    pub async fn set(&mut self, channel: &GuildChannel) -> serenity::Result<()> {
         self.delete().await;
 
-        let thread = CreateThread::new("Board").kind(ChannelType::PublicThread);
-        let thread_channel = channel
-            .create_thread(&self.http, thread)
-            .await
-            .unwrap();
+        // reuse the thread created before restart instead of making another one
+        let thread_channel = match self.find_thread(channel).await {
+            Some(thread) => thread,
+            None => {
+                let thread = CreateThread::new(BOARD_NAME).kind(ChannelType::PublicThread);
+                channel
+                    .create_thread(&self.http, thread)
+                    .await?
+            }
+        };
         
-        let score_embed = CreateEmbed::new().title("Score");
-        let score = thread_channel
-            .send_message(&self.http, CreateMessage::new().add_embed(score_embed))
-            .await
-            .unwrap();
+        let lang = self.lang();
+        let score = match self.find_message(&thread_channel, "board.score").await {
+            Some(score) => score,
+            None => {
+                let score_embed = CreateEmbed::new().title(t(lang, "board.score"));
+                thread_channel
+                    .send_message(&self.http, CreateMessage::new().add_embed(score_embed))
+                    .await?
+            }
+        };
 
-        let status_embed = CreateEmbed::new().title("재생중인 곡");
-        let status = thread_channel
-            .send_message(&self.http, CreateMessage::new().add_embed(status_embed))
-            .await
-            .unwrap();
+        let status = match self.find_message(&thread_channel, "board.now_playing").await {
+            Some(status) => status,
+            None => {
+                let status_embed = CreateEmbed::new().title(t(lang, "board.now_playing"));
+                thread_channel
+                    .send_message(&self.http, CreateMessage::new().add_embed(status_embed))
+                    .await?
+            }
+        };
 
+        self.renderer = Some(BoardRenderer::spawn(
+            self.guild_id,
+            self.http.clone(),
+            thread_channel.clone(),
+            score.id,
+            status.id,
+        ));
         self.thread = Some(thread_channel);
         self.score_message = Some(score);
         self.status_message = Some(status);
+        self.edit_score();
+        self.refresh_status();
+        Ok(())
+    }
+
+    /// Finds a "Board" thread under `channel` which was created by this bot.
+    /// Archived threads are unarchived before being returned.
+    async fn find_thread(&self, channel: &GuildChannel) -> Option<GuildChannel> {
+        let bot_id = self.http.get_current_user().await.ok()?.id;
+        let is_board = |thread: &GuildChannel| {
+            thread.parent_id == Some(channel.id)
+                && thread.owner_id == Some(bot_id)
+                && thread.name == BOARD_NAME
+        };
+
+        let active = channel.guild_id
+            .get_active_threads(&self.http)
+            .await
+            .ok()?
+            .threads
+            .into_iter()
+            .find(|thread| is_board(thread));
+        if active.is_some() {
+            return active;
+        }
+
+        let mut archived = channel.id
+            .get_archived_public_threads(&self.http, None, None)
+            .await
+            .ok()?
+            .threads
+            .into_iter()
+            .find(|thread| is_board(thread))?;
+        archived.edit_thread(&self.http, EditThread::new().archived(false))
+            .await
+            .ok()?;
+        Some(archived)
+    }
+
+    /// Finds the bot's message in `thread` whose first embed has the title of `key` in any language,
+    /// so that the board survives a language change.
+    async fn find_message(&self, thread: &GuildChannel, key: &str) -> Option<Message> {
+        let bot_id = self.http.get_current_user().await.ok()?.id;
+        let titles = Lang::ALL.map(|lang| t(lang, key));
+        thread.messages(&self.http, GetMessages::new().limit(50))
+            .await
+            .ok()?
+            .into_iter()
+            .find(|message| {
+                message.author.id == bot_id
+                    && message.embeds.first()
+                        .and_then(|embed| embed.title.as_deref())
+                        .is_some_and(|title| titles.iter().any(|t| t == title))
+            })
+    }
+
+    fn lang(&self) -> Lang {
+        Lang::of(Some(self.guild_id))
+    }
+
+    /// Posts the final scores and archives the thread.
+    pub async fn finish(&mut self) {
+        metrics::queue_length(self.guild_id, 0);
+        self.now_playing = None;
+        self.refresh_status();
+        self.close_round().await;
+        if let Some(renderer) = self.renderer.take() {
+            let embed = CreateEmbed::new().title(t(self.lang(), "board.final_score")).description(self.score_text.clone());
+            renderer.finish(embed);
+        }
+        self.thread = None;
+        self.score_message = None;
+        self.status_message = None;
+    }
+
+    /// Queues an edit of the score message.
+    pub fn edit_score(&self) {
+        if let Some(renderer) = &self.renderer {
+            let embed = CreateEmbed::new().title(t(self.lang(), "board.score")).description(self.score_text.clone());
+            renderer.score(embed);
+        }
+    }
+
+    /// Shows `score_text` on the score message, see [`Game::score_text`].
+    ///
+    /// [`Game::score_text`]: quiz_core::game::Game::score_text
+    pub fn show_scores(&mut self, score_text: String) {
+        self.score_text = score_text;
+        self.edit_score();
+    }
+
+    /// Shows `meta` as the playing song, `None` when nothing is playing.
+    ///
+    /// Pause state and skip votes belong to the previous song and are reset.
+    pub fn edit_status(&mut self, meta: Option<MetaData>) {
+        self.now_playing = meta.map(|meta| {
+            let ends_at = Utc::now().timestamp() + meta.duration.unwrap_or(0);
+            (meta, ends_at)
+        });
+        self.paused_remaining = None;
+        self.skip_votes.clear();
+        self.refresh_status();
+    }
+
+    pub fn set_queue_len(&mut self, queue_len: usize) {
+        metrics::queue_length(self.guild_id, queue_len);
+        if self.queue_len != queue_len {
+            self.queue_len = queue_len;
+            self.refresh_status();
+        }
     }
 
-    pub async fn edit_score(&mut self) {
-        if let Some(score) = &mut self.score_message {
-            let mut score_vec: Vec<(i32, String)> = self.score.iter().map(|(k, &v)| (v, k.clone())).collect();
-            score_vec.sort_by(|a, b| b.0.cmp(&a.0));
-            let score_text = score_vec.iter().map(|(v, k)| format!("{}: {}", k, v)).collect::<Vec<String>>().join("\n");
-            score.edit(&self.http, 
-                EditMessage::new().add_embed(CreateEmbed::new().title("Score").description(score_text))).await.unwrap();
+    /// Queues an edit of the status message to the current state.
+    pub fn refresh_status(&self) {
+        if let Some(renderer) = &self.renderer {
+            renderer.status(self.status_view());
         }
     }
 
-    pub async fn edit_status(&mut self, meta: Option<MetaData>) {
-        if let Some(status) = &mut self.status_message {
-            let embed = match meta {
-                Some(meta) => {
-                    let after = Utc::now().timestamp() + meta.duration.unwrap();
-                    CreateEmbed::new()
-                        .title("재생중인 곡")
-                        .description(format!("{}\n<t:{}:R> 종료", meta.title.unwrap(), after))
-                }, 
-                None => {
-                    CreateEmbed::new().title("재생중인 곡").description("없음")
-                }
-            };
-            status.edit(&self.http, EditMessage::new().add_embed(embed))
-                .await
-                .unwrap();
+    fn status_view(&self) -> StatusView {
+        let lang = self.lang();
+        let (title, duration, ends_at) = match &self.now_playing {
+            Some((meta, ends_at)) => (
+                Some(meta.title.clone().unwrap_or(t(lang, "common.untitled"))),
+                meta.duration.unwrap_or(0),
+                *ends_at,
+            ),
+            None => (None, 0, 0),
+        };
+        StatusView {
+            title,
+            duration,
+            ends_at,
+            paused_remaining: self.paused_remaining,
+            votes: self.skip_votes.len(),
+            queue_len: self.queue_len,
+            controls: self.controls(),
         }
     }
 
-    pub async fn add_user(&mut self, member: String) {
-        if self.score.contains_key(&member) {
+    /// Buttons for the status message, none while nothing is playing.
+    pub fn controls(&self) -> Vec<CreateActionRow> {
+        if self.now_playing.is_none() {
+            return Vec::new();
+        }
+        let lang = self.lang();
+        let button = |control: BoardControl, key: &str, style: ButtonStyle| {
+            CreateButton::new(control.custom_id()).label(t(lang, key)).style(style)
+        };
+        let pause = if self.is_paused() { "control.resume" } else { "control.pause" };
+        vec![CreateActionRow::Buttons(vec![
+            button(BoardControl::PauseResume, pause, ButtonStyle::Primary),
+            button(BoardControl::Skip, "control.skip", ButtonStyle::Secondary),
+            button(BoardControl::VoteSkip, "control.vote_skip", ButtonStyle::Secondary),
+            button(BoardControl::Hint, "control.hint", ButtonStyle::Success),
+            button(BoardControl::Stop, "control.stop", ButtonStyle::Danger),
+        ])]
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_remaining.is_some()
+    }
+
+    /// Marks the song paused or resumed with `remaining` seconds left.
+    pub fn set_paused(&mut self, paused: bool, remaining: i64) {
+        self.paused_remaining = paused.then_some(remaining);
+        if let Some((_, ends_at)) = &mut self.now_playing {
+            *ends_at = Utc::now().timestamp() + remaining;
+        }
+        self.refresh_status();
+    }
+
+    /// Adds the vote of `user_id` to skip the playing song and returns the number of votes.
+    pub fn vote_skip(&mut self, user_id: UserId) -> usize {
+        self.skip_votes.insert(user_id);
+        self.refresh_status();
+        self.skip_votes.len()
+    }
+
+    /// Keyword of the playing song with every letter but the first hidden.
+    pub fn hint(&self) -> Option<String> {
+        let keyword = self.now_playing.as_ref()?.0.keyword.as_ref()?;
+        let hint = keyword.chars()
+            .enumerate()
+            .map(|(index, c)| if index == 0 || c.is_whitespace() { c } else { '○' })
+            .collect();
+        Some(hint)
+    }
+
+    /// Closes the running round and, in a multiple-choice mode, opens one for `meta`.
+    ///
+    /// Decoys are drawn from `queued_answers` and the answers of songs played before,
+    /// then from the saved quiz sets when the game has too few songs.
+    /// Without any decoy no round is opened and the song is answered by typing.
+    pub async fn start_round(&mut self, meta: Option<&MetaData>, queued_answers: Vec<String>) {
+        self.close_round().await;
+
+        if guild_settings::get(self.guild_id).answer_mode == AnswerMode::Typing {
+            return;
+        }
+        let (Some(thread), Some(answer)) = (&self.thread, meta.and_then(MetaData::answer)) else {
             return;
+        };
+
+        let mut pool = queued_answers;
+        pool.extend(self.played_answers.iter().cloned());
+        self.played_answers.push(answer.clone());
+        let decoys = pool.iter().filter(|option| **option != answer).collect::<HashSet<_>>().len();
+        if decoys < choice_round::DECOYS {
+            pool.extend(quiz_store::answers().await);
+        }
+
+        let lang = self.lang();
+        let Some(round) = ChoiceRound::new(answer, &pool) else {
+            let notice = CreateMessage::new().content(t(lang, "choice.typing"));
+            if let Err(why) = thread.send_message(&self.http, notice).await {
+                warn!(error = ?why, "Failed to send typing notice");
+            }
+            return;
+        };
+        let message = CreateMessage::new()
+            .embed(CreateEmbed::new().title(t(lang, "choice.title")))
+            .components(vec![round.menu(t(lang, "choice.placeholder"))]);
+        match thread.send_message(&self.http, message).await {
+            Ok(message) => {
+                self.round = Some(round);
+                self.round_message = Some(message);
+            },
+            Err(why) => warn!(error = ?why, "Failed to send choices"),
         }
-        self.score.insert(member, 0);
-        self.edit_score().await;
     }
 
-    pub async fn add_users(&mut self, members: Vec<String>) {
-        for member in members {
-            if self.score.contains_key(&member) {
-                continue;
+    /// Closes the running round, revealing the answer on its message.
+    pub async fn close_round(&mut self) {
+        let Some(mut round) = self.round.take() else {
+            return;
+        };
+        round.close();
+
+        let lang = self.lang();
+        if let Some(mut message) = self.round_message.take() {
+            let embed = CreateEmbed::new()
+                .title(t(lang, "choice.title"))
+                .description(tr(lang, "choice.answer", &[("answer", round.answer().to_owned())]));
+            let edit = EditMessage::new()
+                .embed(embed)
+                .components(vec![round.menu(t(lang, "choice.placeholder"))]);
+            if let Err(why) = message.edit(&self.http, edit).await {
+                warn!(error = ?why, "Failed to close choices");
             }
-            self.score.insert(member, 0);
         }
-        self.edit_score().await;
     }
 
-    pub async fn add_score(&mut self, member: String) {
-        if let Some(value) = self.score.get_mut(&member) {
-            *value += 1;
-            self.edit_score().await;
+    /// Whether typed answers count, which they do unless a multiple-choice round is open.
+    pub fn takes_typed_answers(&self) -> bool {
+        guild_settings::get(self.guild_id).answer_mode == AnswerMode::Typing || self.round.is_none()
+    }
+
+    /// Records the pick of `user_id` on the menu with `custom_id`.
+    pub fn pick(&mut self, custom_id: &str, user_id: UserId, option: usize) -> Pick {
+        let Some(round) = &mut self.round else {
+            return Pick::Closed;
+        };
+        round.pick(custom_id, user_id, option)
+    }
+
+    /// Text of the option at `option` on the menu of the running round.
+    pub fn choice(&self, option: usize) -> Option<String> {
+        self.round.as_ref()?.option(option).map(str::to_owned)
+    }
+
+    /// Posts a summary of the guesses in `round`, the round of the song `meta` which just ended.
+    pub async fn sum_up(&self, meta: &MetaData, round: &Round<UserId>) {
+        let Some(thread) = &self.thread else {
+            return;
+        };
+
+        let embeds = vec![self.reveal_embed(meta), self.summary_embed(round.answer.clone(), meta, &round.log)];
+        if let Err(why) = thread.send_message(&self.http, CreateMessage::new().embeds(embeds)).await {
+            warn!(error = ?why, "Failed to send round summary");
+        }
+    }
+
+    /// Reveals the song which was played, with its thumbnail and where it is from.
+    fn reveal_embed(&self, meta: &MetaData) -> CreateEmbed {
+        let lang = self.lang();
+        let mut embed = CreateEmbed::new()
+            .title(meta.title.clone().unwrap_or(t(lang, "common.untitled")))
+            .colour(serenity::Colour::DARK_GREEN);
+        if let Some(url) = &meta.webpage_url {
+            embed = embed.url(url);
+        }
+        if let Some(thumbnail) = &meta.thumbnail {
+            embed = embed.image(thumbnail);
+        }
+        if let Some(artist) = &meta.artist {
+            embed = embed.field(t(lang, "reveal.artist"), artist, true);
+        }
+        if let Some(album) = &meta.album {
+            embed = embed.field(t(lang, "reveal.album"), album, true);
+        }
+        if let Some(year) = meta.year {
+            embed = embed.field(t(lang, "reveal.year"), year.to_string(), true);
+        }
+        embed
+    }
+
+    fn summary_embed(&self, answer: String, meta: &MetaData, log: &GuessLog<UserId>) -> CreateEmbed {
+        let lang = self.lang();
+        let winners = log.correct()
+            .iter()
+            .map(|(guess, seconds)| tr(lang, "summary.winner", &[
+                ("name", guess.name.clone()),
+                ("seconds", format!("{:.1}", seconds)),
+            ]))
+            .collect::<Vec<String>>();
+        let winners = if winners.is_empty() { t(lang, "summary.nobody") } else { winners.join("\n") };
+        let counts = tr(lang, "summary.counts", &[
+            ("correct", log.count(Verdict::Correct).to_string()),
+            ("close", log.count(Verdict::Close).to_string()),
+            ("wrong", log.count(Verdict::Wrong).to_string()),
+        ]);
+
+        let embed = CreateEmbed::new()
+            .title(t(lang, "summary.title"))
+            .field(t(lang, "summary.answer"), answer, false)
+            .field(t(lang, "summary.winners"), winners, false)
+            .field(t(lang, "summary.guesses"), counts, false);
+        match &meta.webpage_url {
+            Some(url) => embed.url(url),
+            None => embed,
+        }
+    }
+
+    /// Sends a plain message to the board thread.
+    pub async fn notify(&self, content: &str) {
+        if let Some(thread) = &self.thread {
+            if let Err(why) = thread.say(&self.http, content).await {
+                warn!(error = ?why, "Failed to notify board");
+            }
         }
     }
 
-    pub async fn reset_score(&mut self) {
-        self.score.clear();
-        self.edit_score().await;
+    /// Sends the message of the catalog `key` to the board thread.
+    pub async fn notify_key(&self, key: &str) {
+        self.notify(&t(self.lang(), key)).await;
     }
 
 }
\ No newline at end of file
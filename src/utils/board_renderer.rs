@@ -0,0 +1,206 @@
+use chrono::Utc;
+use poise::serenity_prelude as serenity;
+use serenity::{
+    CreateActionRow, CreateEmbed, CreateEmbedFooter, CreateMessage, EditMessage, EditThread, GuildChannel, GuildId,
+    Http, MessageId,
+};
+
+use tokio::{sync::mpsc, time::Instant};
+use tracing::warn;
+use std::{sync::Arc, time::Duration};
+
+use crate::i18n::{t, tr, Lang};
+
+/// Minimum time between two rounds of edits, updates arriving meanwhile are coalesced.
+const MIN_EDIT_INTERVAL: Duration = Duration::from_secs(2);
+/// How often the progress bar moves while a song is playing.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+const PROGRESS_WIDTH: i64 = 16;
+
+/// What the status message shows, captured from `Board`.
+#[derive(Clone, Debug, Default)]
+pub struct StatusView {
+    /// Title of the playing song, `None` while nothing is playing.
+    pub title: Option<String>,
+    pub duration: i64,
+    /// When the song ends, as a unix timestamp.
+    pub ends_at: i64,
+    /// Seconds left when the song was paused, `None` while playing.
+    pub paused_remaining: Option<i64>,
+    pub votes: usize,
+    pub queue_len: usize,
+    pub controls: Vec<CreateActionRow>,
+}
+
+impl StatusView {
+    fn is_playing(&self) -> bool {
+        self.title.is_some() && self.paused_remaining.is_none()
+    }
+}
+
+enum Render {
+    Score(CreateEmbed),
+    Status(StatusView),
+    Finish(CreateEmbed),
+}
+
+/// Handle to the task which owns every edit of the board messages of a guild.
+#[derive(Clone, Debug)]
+pub struct BoardRenderer {
+    sender: mpsc::UnboundedSender<Render>,
+}
+
+impl BoardRenderer {
+    /// Spawns the renderer of the board in `thread`.
+    ///
+    /// The task stops after [`Self::finish`] or when every handle is dropped.
+    pub fn spawn(guild_id: GuildId, http: Arc<Http>, thread: GuildChannel, score: MessageId, status: MessageId) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(guild_id, http, thread, score, status, receiver));
+        Self { sender }
+    }
+
+    pub fn score(&self, embed: CreateEmbed) {
+        drop(self.sender.send(Render::Score(embed)));
+    }
+
+    pub fn status(&self, view: StatusView) {
+        drop(self.sender.send(Render::Status(view)));
+    }
+
+    /// Applies pending edits, posts `final_score` and archives the thread.
+    pub fn finish(&self, final_score: CreateEmbed) {
+        drop(self.sender.send(Render::Finish(final_score)));
+    }
+}
+
+/// Updates received since the last round of edits.
+#[derive(Default)]
+struct Pending {
+    score: Option<CreateEmbed>,
+    status: StatusView,
+    status_dirty: bool,
+    finish: Option<CreateEmbed>,
+}
+
+impl Pending {
+    fn apply(&mut self, render: Render) {
+        match render {
+            Render::Score(embed) => self.score = Some(embed),
+            Render::Status(view) => {
+                self.status = view;
+                self.status_dirty = true;
+            },
+            Render::Finish(embed) => self.finish = Some(embed),
+        }
+    }
+}
+
+async fn run(
+    guild_id: GuildId,
+    http: Arc<Http>,
+    thread: GuildChannel,
+    score_id: MessageId,
+    status_id: MessageId,
+    mut receiver: mpsc::UnboundedReceiver<Render>,
+) {
+    let mut pending = Pending::default();
+    let mut last_edit: Option<Instant> = None;
+
+    loop {
+        if pending.status.is_playing() {
+            match tokio::time::timeout(PROGRESS_INTERVAL, receiver.recv()).await {
+                Ok(Some(render)) => pending.apply(render),
+                Ok(None) => break,
+                // a tick of the progress bar
+                Err(_) => pending.status_dirty = true,
+            }
+        } else {
+            match receiver.recv().await {
+                Some(render) => pending.apply(render),
+                // the board was dropped
+                None => break,
+            }
+        }
+
+        // wait out the rate limit, collecting what arrives meanwhile
+        if let Some(last_edit) = last_edit {
+            tokio::time::sleep_until(last_edit + MIN_EDIT_INTERVAL).await;
+        }
+        while let Ok(render) = receiver.try_recv() {
+            pending.apply(render);
+        }
+
+        let lang = Lang::of(Some(guild_id));
+        if let Some(embed) = pending.score.take() {
+            if let Err(why) = thread.id.edit_message(&http, score_id, EditMessage::new().embed(embed)).await {
+                warn!(error = ?why, "Failed to edit score");
+            }
+        }
+        if pending.status_dirty {
+            pending.status_dirty = false;
+            let edit = EditMessage::new()
+                .embed(status_embed(lang, &pending.status))
+                .components(pending.status.controls.clone());
+            if let Err(why) = thread.id.edit_message(&http, status_id, edit).await {
+                warn!(error = ?why, "Failed to edit status");
+            }
+        }
+        last_edit = Some(Instant::now());
+
+        if let Some(final_score) = pending.finish.take() {
+            if let Err(why) = thread.send_message(&http, CreateMessage::new().add_embed(final_score)).await {
+                warn!(error = ?why, "Failed to send final score");
+            }
+            let mut thread = thread.clone();
+            if let Err(why) = thread.edit_thread(&http, EditThread::new().archived(true)).await {
+                warn!(error = ?why, "Failed to archive board thread");
+            }
+            break;
+        }
+    }
+}
+
+fn status_embed(lang: Lang, view: &StatusView) -> CreateEmbed {
+    let embed = CreateEmbed::new()
+        .title(t(lang, "board.now_playing"))
+        .footer(CreateEmbedFooter::new(footer(lang, view)));
+    let Some(title) = &view.title else {
+        return embed.description(t(lang, "common.none"));
+    };
+
+    let remaining = view.paused_remaining
+        .unwrap_or(view.ends_at - Utc::now().timestamp())
+        .clamp(0, view.duration);
+    let elapsed = view.duration - remaining;
+    let mut description = format!("{}\n{} {} / {}",
+        title,
+        progress_bar(elapsed, view.duration),
+        clock(elapsed),
+        clock(view.duration));
+    if view.paused_remaining.is_some() {
+        description.push('\n');
+        description.push_str(&t(lang, "board.paused"));
+    }
+    embed.description(description)
+}
+
+fn footer(lang: Lang, view: &StatusView) -> String {
+    let mut footer = tr(lang, "board.queue_length", &[("count", view.queue_len.to_string())]);
+    if view.votes > 0 {
+        footer.push_str(" · ");
+        footer.push_str(&tr(lang, "board.votes", &[("count", view.votes.to_string())]));
+    }
+    footer
+}
+
+fn progress_bar(elapsed: i64, duration: i64) -> String {
+    let position = if duration > 0 { elapsed * (PROGRESS_WIDTH - 1) / duration } else { 0 };
+    (0..PROGRESS_WIDTH)
+        .map(|index| if index == position { "🔘" } else { "▬" })
+        .collect()
+}
+
+fn clock(seconds: i64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
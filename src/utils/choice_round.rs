@@ -0,0 +1,116 @@
+use poise::serenity_prelude as serenity;
+use serenity::{CreateActionRow, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, UserId};
+
+use rand::seq::SliceRandom;
+use std::{collections::HashSet, sync::atomic::{AtomicU64, Ordering}};
+
+/// Number of wrong options shown with the answer.
+pub const DECOYS: usize = 3;
+
+// ids of the rounds of every guild, so that a menu left from another game never matches
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A round of the multiple-choice mode, one per song.
+#[derive(Debug, Clone)]
+pub struct ChoiceRound {
+    id: u64,
+    options: Vec<String>,
+    // index of the answer in `options`
+    answer: usize,
+    picked: HashSet<UserId>,
+    open: bool,
+}
+
+/// Outcome of a pick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pick {
+    Correct,
+    Wrong,
+    AlreadyPicked,
+    Closed,
+}
+
+impl ChoiceRound {
+    const PREFIX: &'static str = "choice:";
+
+    /// Makes a round for `answer` with decoys drawn from `pool`.
+    ///
+    /// Returns `None` when `pool` has no decoy, as a menu of the answer alone would give it away.
+    pub fn new(answer: String, pool: &[String]) -> Option<Self> {
+        let mut rng = rand::thread_rng();
+        let mut candidates = pool.iter()
+            .filter(|option| **option != answer)
+            .cloned()
+            .collect::<Vec<String>>();
+        candidates.sort();
+        candidates.dedup();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let mut options = candidates
+            .choose_multiple(&mut rng, DECOYS)
+            .cloned()
+            .collect::<Vec<String>>();
+        options.push(answer.clone());
+        options.shuffle(&mut rng);
+        let answer = options.iter().position(|option| *option == answer).unwrap_or_default();
+
+        Some(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            options,
+            answer,
+            picked: HashSet::new(),
+            open: true,
+        })
+    }
+
+    pub fn is_choice(custom_id: &str) -> bool {
+        custom_id.starts_with(Self::PREFIX)
+    }
+
+    pub fn custom_id(&self) -> String {
+        format!("{}{}", Self::PREFIX, self.id)
+    }
+
+    pub fn answer(&self) -> &str {
+        &self.options[self.answer]
+    }
+
+    pub fn option(&self, index: usize) -> Option<&str> {
+        self.options.get(index).map(String::as_str)
+    }
+
+    /// Records the pick of `user_id`, each user picks once per round.
+    pub fn pick(&mut self, custom_id: &str, user_id: UserId, option: usize) -> Pick {
+        if !self.open || custom_id != self.custom_id() {
+            return Pick::Closed;
+        }
+        if !self.picked.insert(user_id) {
+            return Pick::AlreadyPicked;
+        }
+        if option == self.answer {
+            Pick::Correct
+        } else {
+            Pick::Wrong
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Select menu listing the options, disabled once the round is closed.
+    pub fn menu(&self, placeholder: String) -> CreateActionRow {
+        let options = self.options.iter()
+            .enumerate()
+            // labels are limited to 100 characters
+            .map(|(index, option)| CreateSelectMenuOption::new(option.chars().take(100).collect::<String>(), index.to_string()))
+            .collect();
+        CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(self.custom_id(), CreateSelectMenuKind::String { options })
+                .placeholder(placeholder)
+                .disabled(!self.open)
+        )
+    }
+}
@@ -1,59 +1,50 @@
+//! Session of a guild: its queue, its board and the game on it, owned by one task.
+//!
+//! The rules live in a [`quiz_core::session::Session`] which follows the queue of tracks song by song.
+//! The task turns what the session decides into board and voice actions.
+//!
+//! [`GuildQueue`] is a handle which sends [`Command`]s to the task of its guild.
+//! Songbird events are forwarded to the same channel, so commands and events are handled
+//! one at a time in the order they were sent, and no lock is shared between the queue and the board.
+//! A session can be driven in tests by sending a scripted sequence of commands.
+
 use songbird::{
     driver::Driver,
     events::{Event, EventContext, EventData, EventHandler, TrackEvent},
-    input::Input,
-    tracks::{Track, TrackHandle, TrackResult, TrackQueue},
+    input::{AudioStreamError, Input},
+    tracks::{PlayMode, Track, TrackHandle, TrackResult},
+    Songbird,
     id::ChannelId,
 };
 
 use poise::serenity_prelude as serenity;
-use serenity::{async_trait, Context, GuildId, GuildChannel, Http};
+use serenity::{async_trait, Context, GuildId, GuildChannel, Http, Channel, ChannelType, UserId};
 
 use crate::{
     GuildQueueKey,
-    utils::youtube_dl::MetaData,
-    global::*,
+    metrics,
+    utils::youtube_dl::{evict, prepare_interval, prepare_source, MetaData, TrackSource},
 };
 
-use lazy_static::lazy_static;
+use quiz_core::{answer::Verdict, clock::SystemClock, game::Event as GameEvent, session::Session};
+use tokio::sync::{mpsc::{self, error::SendError, UnboundedReceiver, UnboundedSender, WeakUnboundedSender}, oneshot};
 use tracing::{info, warn};
-use parking_lot::Mutex;
-use std::{collections::VecDeque, ops::Deref, sync::Arc, time::Duration, io::Read, process::Command};
-
-use super::board::Board;
-
-lazy_static! {
-    static ref INTERVAL: Vec<u8> = {
-        let mut f = std::fs::File::open(format!("{TARGET}{NO_SOUND}")).unwrap();
-        let mut buffer = Vec::new();
-        f.read_to_end(&mut buffer).unwrap();
-        buffer
-    };
-
-    static ref INTERVAL_META: MetaData = {
-        let output = Command::new("ffprobe")
-            .args(&[
-                "-i", format!("{TARGET}{NO_SOUND}").as_str(),
-                "-show_entries", "format=duration",
-                "-v", "quiet",
-                "-of", "csv=p=0",
-            ])
-            .output()
-            .expect("ffprobe failed to start");
-        let duration = String::from_utf8(output.stdout).unwrap().trim().parse::<f64>().unwrap() as i64;
-        MetaData {
-            duration: Some(duration),
-            title: Some("interval".to_owned()),
-            keyword: None,
-        }
-    };
-}
+use std::{collections::{HashMap, VecDeque}, ops::Deref, sync::Arc, time::Duration};
 
-// Modified copy from songbird/src/tracks/queue.rs
+use super::{
+    board::Board,
+    choice_round::Pick,
+    guild_settings::{self, AnswerMode},
+    session_store::{self, SavedSession},
+};
 
+/// Handle to the session task of a guild, cheap to clone.
+///
+/// Requests wait for the reply of the task, the rest return once the command is sent.
+/// After the session has ended requests get an empty reply.
 #[derive(Clone, Debug)]
 pub struct GuildQueue {
-    inner: Arc<Mutex<GuildQueueCore>>,
+    sender: UnboundedSender<Command>,
 }
 
 pub async fn get_guild_queue(ctx: &Context, guild_id: GuildId) -> GuildQueue {
@@ -61,13 +52,31 @@ pub async fn get_guild_queue(ctx: &Context, guild_id: GuildId) -> GuildQueue {
         let data_read = ctx.data.read().await;
         data_read.get::<GuildQueueKey>().unwrap().clone()
     };
+    let voice = songbird::get(ctx).await;
     let guild_queue = guild_queue_map
         .entry(guild_id)
-        .or_insert_with(|| GuildQueue::new(ctx.http.clone()))
+        .or_insert_with(|| GuildQueue::new(guild_id, ctx.http.clone(), voice))
         .clone();
     guild_queue
 }
 
+/// Returns the queue of the guild without creating one.
+pub async fn find_guild_queue(ctx: &Context, guild_id: GuildId) -> Option<GuildQueue> {
+    let guild_queue_map = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<GuildQueueKey>().unwrap().clone()
+    };
+    guild_queue_map.get(&guild_id).map(|guild_queue| guild_queue.clone())
+}
+
+pub async fn remove_guild_queue(ctx: &Context, guild_id: GuildId) -> Option<GuildQueue> {
+    let guild_queue_map = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<GuildQueueKey>().unwrap().clone()
+    };
+    guild_queue_map.remove(&guild_id).map(|(_, guild_queue)| guild_queue)
+}
+
 #[derive(Debug)]
 pub struct Queued(TrackHandle);
 
@@ -87,114 +96,263 @@ impl Queued {
     }
 }
 
-#[derive(Debug)]
-pub struct GuildQueueCore {
-    // Track Queue
+/// Messages handled by the session task of a guild, in the order they were sent.
+///
+/// Variants with a `reply` are requests, the task answers them once handled.
+pub enum Command {
+    /// `track` ended, `errored` if it failed to play. Sent by songbird.
+    TrackEnded { track: TrackHandle, errored: bool },
+    /// `track` failed to play and is about to end. Sent by songbird.
+    TrackFailed { track: TrackHandle, error: String, retry: bool },
+    /// The playing track is about to end, the next one is loaded. Sent by songbird.
+    Preload,
+    /// A song which failed to play was prepared again with an interval, to be played next.
+    Requeue {
+        interval: TrackHandle,
+        interval_meta: Box<MetaData>,
+        track: TrackHandle,
+        meta: Box<MetaData>,
+    },
+    /// A song which failed to play could not be prepared again.
+    RetryFailed,
+
+    /// Queues `track` after `interval`, both already added paused to the driver.
+    Enqueue {
+        interval: TrackHandle,
+        interval_meta: Box<MetaData>,
+        track: TrackHandle,
+        meta: Box<MetaData>,
+        with_interval: bool,
+        reply: oneshot::Sender<()>,
+    },
+    Current { reply: oneshot::Sender<Option<(TrackHandle, MetaData)>> },
+    Queue { reply: oneshot::Sender<Vec<(TrackHandle, MetaData)>> },
+    /// Removes the song at `index` along with the interval in front of it.
+    RemoveSong { index: usize, reply: oneshot::Sender<Option<MetaData>> },
+    Skip { reply: oneshot::Sender<TrackResult<()>> },
+    TogglePause { reply: oneshot::Sender<TrackResult<()>> },
+    /// Skips once more than half of the `listeners` voted.
+    VoteSkip { user_id: UserId, listeners: usize, reply: oneshot::Sender<TrackResult<()>> },
+    /// Stops the playing track and clears the queue.
+    Stop,
+    /// Stops the queue, posts the final scores and ends the task.
+    End { reply: oneshot::Sender<()> },
+
+    RegisterChannel { channel: Box<GuildChannel>, reply: oneshot::Sender<serenity::Result<()>> },
+    RegisterChannelIfEmpty { channel_id: serenity::ChannelId, reply: oneshot::Sender<()> },
+    Channel { reply: oneshot::Sender<Option<ChannelId>> },
+    ClaimHost { user_id: UserId },
+    Host { reply: oneshot::Sender<Option<UserId>> },
+    SavedSession { reply: oneshot::Sender<SavedSession> },
+    /// Sends the message of the catalog `key` to the board thread.
+    Notify { key: &'static str, reply: oneshot::Sender<()> },
+
+    /// Judges a chat message of `user_id`, shown as `name`, as an answer. `None` when it is not taken as one.
+    Answer {
+        user_id: UserId,
+        channel_id: serenity::ChannelId,
+        name: String,
+        text: String,
+        reply: oneshot::Sender<Option<Verdict>>,
+    },
+    /// Pick on the menu `custom_id` of a multiple-choice round.
+    Pick { custom_id: String, user_id: UserId, name: String, option: usize, reply: oneshot::Sender<Pick> },
+    Hint { reply: oneshot::Sender<Option<String>> },
+    AddPlayers { names: Vec<String> },
+    JoinSmallestTeam { user_id: UserId, name: String },
+    CreateTeam { name: String, answer_channel: Option<serenity::ChannelId>, reply: oneshot::Sender<bool> },
+    AddToTeam { team: String, user_id: UserId, name: String, reply: oneshot::Sender<bool> },
+    BalanceTeams { count: usize, users: Vec<(UserId, String)>, team_name: Box<dyn Fn(usize) -> String + Send> },
+    ClearTeams,
+    TeamNames { reply: oneshot::Sender<Vec<String>> },
+    #[cfg(any(feature = "admin-api", test))]
+    Scores { reply: oneshot::Sender<HashMap<String, i32>> },
+    RestoreScores { scores: HashMap<String, i32> },
+}
+
+/// State of a guild session, owned by its task.
+struct GuildSession {
+    guild_id: GuildId,
+    // Track Queue, in step with the songs of `session`: the head is its playing song
     tracks: VecDeque<(Queued, MetaData)>,
-    // bot only reads the messages from this channel 
+    // rounds, scores and teams of the songs in `tracks`
+    session: Session<UserId>,
+    // answer channels of the teams which have one, by team name
+    answer_channels: HashMap<String, serenity::ChannelId>,
+    // bot only reads the messages from this channel
     channel: Option<ChannelId>,
-    board: Arc<tokio::sync::Mutex<Board>>,
+    board: Board,
+    // user who started the game, implicitly a host
+    host: Option<UserId>,
+    // calls to play songs prepared again in, `None` in tests
+    voice: Option<Arc<Songbird>>,
+    http: Arc<Http>,
+    // for the tracks prepared again, does not keep the task alive
+    sender: WeakUnboundedSender<Command>,
 }
 
+/// Forwards the end of a track to the session.
 struct QueueHandler {
-    remote_lock: Arc<Mutex<GuildQueueCore>>,
+    sender: UnboundedSender<Command>,
 }
 
 #[async_trait]
 impl EventHandler for QueueHandler {
     async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
-        let (board_lock, meta) = {
-            let mut inner = self.remote_lock.lock();
-
-            // Due to possibility that users might remove, reorder,
-            // or dequeue+stop tracks, we need to verify that the FIRST
-            // track is the one who has ended.
-            match ctx {
-                EventContext::Track(ts) => {
-                    // This slice should have exactly one entry.
-                    // If the ended track has same id as the queue head, then
-                    // we can progress the queue.
-                    if inner.tracks.front()?.0.uuid() != ts.first()?.1.uuid() {
-                        return None;
-                    }
-                },
-                _ => return None,
-            }
+        // This slice should have exactly one entry.
+        let EventContext::Track(ts) = ctx else {
+            return None;
+        };
+        let (state, handle) = ts.first()?;
+        let errored = matches!(state.playing, PlayMode::Errored(_));
+        drop(self.sender.send(Command::TrackEnded { track: (*handle).clone(), errored }));
+        None
+    }
+}
 
-            let _old = inner.tracks.pop_front();
-
-            info!("Queued track ended: {:?}.", ctx);
-            info!("{} tracks remain.", inner.tracks.len());
-
-            // Keep going until we find one track which works, or we run out.
-            loop {
-                match inner.tracks.front() {
-                    Some(new) => {
-                        if new.0.play().is_err() {
-                            // Discard files which cannot be used for whatever reason.
-                            warn!("Track in Queue couldn't be played...");
-                            inner.tracks.pop_front();
-                        } else {
-                            break (inner.board.clone(), Some(new.1.clone()));
-                        }
-                    },
-                    None => break (inner.board.clone(), None),
-                }
-            }
+/// Forwards a track which failed to play to the session, see [`GuildSession::track_failed`].
+///
+/// Songbird fires the error before the end of the track, so the session sees them in that order.
+struct TrackErrorHandler {
+    sender: UnboundedSender<Command>,
+    // `false` for a track which already was prepared again
+    retry: bool,
+}
+
+#[async_trait]
+impl EventHandler for TrackErrorHandler {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(ts) = ctx else {
+            return None;
         };
-        let mut board = board_lock.lock().await;
-        board.edit_status(meta).await;
+        let (state, handle) = ts.first()?;
+        let PlayMode::Errored(why) = &state.playing else {
+            return None;
+        };
+        drop(self.sender.send(Command::TrackFailed {
+            track: (*handle).clone(),
+            error: why.to_string(),
+            retry: self.retry,
+        }));
         None
     }
 }
 
 struct SongPreloader {
-    remote_lock: Arc<Mutex<GuildQueueCore>>,
+    sender: UnboundedSender<Command>,
 }
 
 #[async_trait]
 impl EventHandler for SongPreloader {
     async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
-        let inner = self.remote_lock.lock();
+        drop(self.sender.send(Command::Preload));
+        None
+    }
+}
+
+/// Answers a request, the requester may have stopped waiting.
+fn respond<T>(reply: oneshot::Sender<T>, value: T) {
+    drop(reply.send(value));
+}
+
+/// Moves the queue on when `track` ends and reports it when it fails,
+/// preparing it again if `retry`.
+fn subscribe(track: &mut Track, sender: &UnboundedSender<Command>, retry: bool) {
+    track.events.add_event(
+        EventData::new(Event::Track(TrackEvent::End), QueueHandler { sender: sender.clone() }),
+        Duration::ZERO,
+    );
+    track.events.add_event(
+        EventData::new(Event::Track(TrackEvent::Error), TrackErrorHandler { sender: sender.clone(), retry }),
+        Duration::ZERO,
+    );
+}
 
-        if let Some(track) = inner.tracks.get(1) {
-            // This is the sync-version so that we can fire and ignore
-            // the request ASAP.
-            drop(track.0.make_playable());
+/// Downloads and cuts `source` of a song which failed to play again, and sends it to be played next.
+///
+/// Runs beside the session, which keeps handling commands meanwhile.
+async fn prepare_again(guild_id: GuildId, source: TrackSource, voice: Option<Arc<Songbird>>, sender: UnboundedSender<Command>) {
+    evict(&source.url).await;
+    let prepared = match prepare_source(&source).await {
+        Ok(song) => prepare_interval().await.map(|interval| (interval, song)),
+        Err(why) => Err(why),
+    };
+    let ((interval, interval_meta), (input, meta)) = match prepared {
+        Ok(prepared) => prepared,
+        Err(why) => {
+            warn!(url = %source.url, error = ?why, "Failed to prepare a track again");
+            drop(sender.send(Command::RetryFailed));
+            return;
         }
+    };
+    // the bot left the voice channel meanwhile
+    let Some(call) = voice.and_then(|voice| voice.get(guild_id)) else {
+        return;
+    };
 
-        None
+    let mut interval = Track::from(interval);
+    subscribe(&mut interval, &sender, false);
+    let mut track = Track::from(input);
+    subscribe(&mut track, &sender, false);
+    let (interval, track) = {
+        let mut call = call.lock().await;
+        (call.play(interval.pause()), call.play(track.pause()))
+    };
+    info!(url = %source.url, "Prepared a track again");
+    let requeue = Command::Requeue { interval, interval_meta: Box::new(interval_meta), track, meta: Box::new(meta) };
+    // the session ended meanwhile
+    if let Err(SendError(Command::Requeue { interval, track, .. })) = sender.send(requeue) {
+        drop(interval.stop());
+        drop(track.stop());
     }
 }
 
 impl GuildQueue {
-    /// Create a new, empty, track queue.
+    /// Starts the task of a new, empty session of `guild_id`.
+    ///
+    /// Songs which fail to play are played again in the calls of `voice`.
     #[must_use]
-    pub fn new(http: Arc<Http>) -> Self {
-        Self {
-            inner: Arc::new(Mutex::new(GuildQueueCore {
-                tracks: VecDeque::new(),
-                channel: None,
-                board: Arc::new(tokio::sync::Mutex::new(Board::new(http))),
-            })),
-        }
+    pub fn new(guild_id: GuildId, http: Arc<Http>, voice: Option<Arc<Songbird>>) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let session = GuildSession {
+            guild_id,
+            tracks: VecDeque::new(),
+            session: Session::new(Arc::new(SystemClock)),
+            answer_channels: HashMap::new(),
+            channel: None,
+            board: Board::new(guild_id, http.clone()),
+            host: None,
+            voice,
+            http,
+            sender: sender.downgrade(),
+        };
+        tokio::spawn(session.run(receiver));
+        Self { sender }
     }
 
-    pub async fn register_channel(&self, channel: &GuildChannel) {
-        let board_lock = {
-            let mut inner = self.inner.lock();
-            let channel_id = channel.id.into();
-            inner.channel = Some(channel_id);
-            inner.board.clone()
-        };
-        
-        let mut board = board_lock.lock().await;
-        board.set(channel).await;
+    /// Sends `command` to the session, nothing happens once it has ended.
+    pub fn send(&self, command: Command) {
+        drop(self.sender.send(command));
+    }
+
+    /// Sends the request made by `command` and waits for the reply, `None` once the session has ended.
+    async fn request<T>(&self, command: impl FnOnce(oneshot::Sender<T>) -> Command) -> Option<T> {
+        let (reply, response) = oneshot::channel();
+        self.send(command(reply));
+        response.await.ok()
+    }
+
+    pub async fn register_channel(&self, channel: &GuildChannel) -> serenity::Result<()> {
+        let channel = Box::new(channel.clone());
+        self.request(|reply| Command::RegisterChannel { channel, reply }).await.unwrap_or(Ok(()))
     }
 
-    pub fn get_board(&self) -> Arc<tokio::sync::Mutex<Board>> {
-        let inner = self.inner.lock();
-        inner.board.clone()
+    /// Registers `channel_id` as the board channel when no channel has been registered yet.
+    ///
+    /// Used by commands which start playback so that the board is shown
+    /// in the channel the command was invoked from.
+    pub async fn register_channel_if_empty(&self, channel_id: serenity::ChannelId) {
+        self.request(|reply| Command::RegisterChannelIfEmpty { channel_id, reply }).await;
     }
 
     /// Adds an audio source to the queue, to be played in the channel managed by `driver`.
@@ -203,7 +361,13 @@ impl GuildQueue {
     /// the [`AuxMetadata`] can be successfully queried for a [`Duration`].
     ///
     /// [`AuxMetadata`]: crate::input::AuxMetadata
-    pub async fn add_source(&self, input: Input, meta: MetaData, driver: &mut Driver, with_interval: bool) -> TrackHandle {
+    pub async fn add_source(
+        &self,
+        input: Input,
+        meta: MetaData,
+        driver: &mut Driver,
+        with_interval: bool,
+    ) -> Result<TrackHandle, AudioStreamError> {
         self.add(input.into(), meta, driver, with_interval).await
     }
 
@@ -216,23 +380,16 @@ impl GuildQueue {
     /// the [`AuxMetadata`] can be successfully queried for a [`Duration`].
     ///
     /// [`AuxMetadata`]: crate::input::AuxMetadata
-    pub async fn add(&self, mut track: Track, meta: MetaData, driver: &mut Driver, with_interval: bool) -> TrackHandle {
-        // preload_time is removed 
-        // because songbird::input::File::aux_metadata is not implemented
-        // so get_preload_time is not alaways return None
-        // let preload_time = Self::get_preload_time(&mut track).await;
-        self.add_with_preload(track, meta, driver, None/*preload_time*/, with_interval)
-    }
-
-    pub(crate) async fn get_preload_time(track: &mut Track) -> Option<Duration> {
-        let meta = match track.input {
-            Input::Lazy(ref mut rec) | Input::Live(_, Some(ref mut rec)) =>
-                rec.aux_metadata().await.ok(),
-            Input::Live(_, None) => None,
-        };
-
-        meta.and_then(|meta| meta.duration)
-            .map(|d| d.saturating_sub(Duration::from_secs(5)))
+    pub async fn add(
+        &self,
+        track: Track,
+        meta: MetaData,
+        driver: &mut Driver,
+        with_interval: bool,
+    ) -> Result<TrackHandle, AudioStreamError> {
+        // Preloading is off because songbird::input::File does not
+        // implement aux_metadata, so the track duration is never known here.
+        self.add_with_preload(track, meta, driver, None, with_interval).await
     }
 
     /// Add an existing [`Track`] to the queue, using a known time to preload the next track.
@@ -244,180 +401,626 @@ impl GuildQueue {
     ///
     /// A `None` value will not ready the next track until this track ends, disabling preload.
     ///
+    /// Fails without queueing anything when the interval in front of the track can not be read.
+    ///
     /// [`AuxMetadata`]: crate::input::AuxMetadata
-    #[inline]
-    pub fn add_with_preload(
+    pub async fn add_with_preload(
         &self,
         mut track: Track,
         meta: MetaData,
         driver: &mut Driver,
         preload_time: Option<Duration>,
         with_interval: bool
-    ) -> TrackHandle {
+    ) -> Result<TrackHandle, AudioStreamError> {
+        let (interval, interval_meta) = prepare_interval().await?;
+
         // Attempts to start loading the next track before this one ends.
         // Idea is to provide as close to gapless playback as possible,
         // while minimising memory use.
         info!("Track added to queue.");
 
-        let remote_lock = self.inner.clone();
-        track.events.add_event(
-            EventData::new(Event::Track(TrackEvent::End), QueueHandler { remote_lock }),
-            Duration::ZERO,
-        );
+        subscribe(&mut track, &self.sender, true);
 
         if let Some(time) = preload_time {
-            let remote_lock = self.inner.clone();
             track.events.add_event(
-                EventData::new(Event::Delayed(time), SongPreloader { remote_lock }),
+                EventData::new(Event::Delayed(time), SongPreloader { sender: self.sender.clone() }),
                 Duration::ZERO,
             );
         }
 
         // for insert interval between tracks
-        let remote_lock = self.inner.clone();
-        let mut interval = Track::from(INTERVAL.as_ref() as &[u8]);
-        interval.events.add_event(
-            EventData::new(Event::Track(TrackEvent::End), QueueHandler { remote_lock }),
-            Duration::ZERO,
-        );
+        let mut interval = Track::from(interval);
+        subscribe(&mut interval, &self.sender, false);
 
-        let (should_play, handle) = {
-            let mut inner = self.inner.lock();
+        let interval = driver.play(interval.pause());
+        let handle = driver.play(track.pause());
+        let track = handle.clone();
+        let interval_meta = Box::new(interval_meta);
+        let meta = Box::new(meta);
+        self.request(|reply| Command::Enqueue { interval, interval_meta, track, meta, with_interval, reply }).await;
+        Ok(handle)
+    }
 
-            let interval_handle = driver.play(interval.pause());
-            inner.tracks.push_back((Queued(interval_handle.clone()), INTERVAL_META.clone()));
+    /// Returns a handle to the currently playing track.
+    pub async fn current(&self) -> Option<(TrackHandle, MetaData)> {
+        self.request(|reply| Command::Current { reply }).await.flatten()
+    }
 
-            let handle = driver.play(track.pause());
-            inner.tracks.push_back((Queued(handle.clone()), meta));
-            
-            if with_interval {
-                (inner.tracks.len() <= 2, interval_handle)
-            } else {
-                (inner.tracks.len() == 1, handle)
-            }
-        };
+    /// Removes the song at `index` along with the interval in front of it.
+    ///
+    /// The track at the head of the queue can not be removed, use [`skip`] instead.
+    ///
+    /// [`skip`]: GuildQueue::skip
+    pub async fn remove_song(&self, index: usize) -> Option<MetaData> {
+        self.request(|reply| Command::RemoveSong { index, reply }).await.flatten()
+    }
 
-        if should_play {
-            drop(handle.play());
-        }
+    /// Returns whether there are no tracks currently in the queue.
+    pub async fn is_empty(&self) -> bool {
+        self.current().await.is_none()
+    }
 
-        handle
+    /// Pauses or resumes the track at the head of the queue.
+    pub async fn toggle_pause(&self) -> TrackResult<()> {
+        self.request(|reply| Command::TogglePause { reply }).await.unwrap_or(Ok(()))
     }
 
-    /// Returns a handle to the currently playing track.
-    #[must_use]
-    pub fn current(&self) -> Option<(TrackHandle, MetaData)> {
-        let inner = self.inner.lock();
+    /// Stop the currently playing track, and clears the queue.
+    pub fn stop(&self) {
+        self.send(Command::Stop);
+    }
 
-        inner.tracks.front().map(|f| (f.0.handle(), f.1.clone()))
+    /// Skip to the next track in the queue, if it exists.
+    pub async fn skip(&self) -> TrackResult<()> {
+        self.request(|reply| Command::Skip { reply }).await.unwrap_or(Ok(()))
     }
 
-    /// Attempts to remove a track from the specified index.
-    ///
-    /// The returned entry can be readded to *this* queue via [`modify_queue`].
+    /// Adds the vote of `user_id` to skip the playing song, skipping it once
+    /// more than half of the `listeners` voted.
+    pub async fn vote_skip(&self, user_id: UserId, listeners: usize) -> TrackResult<()> {
+        self.request(|reply| Command::VoteSkip { user_id, listeners, reply }).await.unwrap_or(Ok(()))
+    }
+
+    /// Stops the queue, posts the final scores to the board and ends the session.
+    pub async fn end(&self) {
+        self.request(|reply| Command::End { reply }).await;
+    }
+
+    /// Returns a list of currently queued tracks.
     ///
-    /// [`modify_queue`]: TrackQueue::modify_queue
-    #[must_use]
-    pub fn dequeue(&self, index: usize) -> Option<Queued> {
-        self.modify_queue(|vq| vq.remove(index)).map(|(q, _)| q)
+    /// Does not allow for modification of the queue, instead returns a snapshot of the queue at the time of calling.
+    pub async fn current_queue(&self) -> Vec<(TrackHandle, MetaData)> {
+        self.request(|reply| Command::Queue { reply }).await.unwrap_or_default()
     }
 
-    /// Returns the number of tracks currently in the queue.
-    #[must_use]
-    pub fn len(&self) -> usize {
-        let inner = self.inner.lock();
+    pub async fn channel(&self) -> Option<ChannelId> {
+        self.request(|reply| Command::Channel { reply }).await.flatten()
+    }
 
-        inner.tracks.len()
+    /// Captures the state to persist for the session of this queue.
+    pub async fn saved_session(&self) -> SavedSession {
+        self.request(|reply| Command::SavedSession { reply }).await.unwrap_or_default()
     }
 
-    /// Returns whether there are no tracks currently in the queue.
-    #[must_use]
-    pub fn is_empty(&self) -> bool {
-        let inner = self.inner.lock();
+    /// Sends the message of the catalog `key` to the board thread.
+    pub async fn notify(&self, key: &'static str) {
+        self.request(|reply| Command::Notify { key, reply }).await;
+    }
+
+    pub async fn host(&self) -> Option<UserId> {
+        self.request(|reply| Command::Host { reply }).await.flatten()
+    }
 
-        inner.tracks.is_empty()
+    /// Makes `user_id` the host if nobody hosts this queue yet.
+    pub fn claim_host(&self, user_id: UserId) {
+        self.send(Command::ClaimHost { user_id });
     }
 
-    /// Allows modification of the inner queue (i.e., deletion, reordering).
+    /// Judges `text`, sent by `user_id` in `channel_id`, as an answer to the playing song,
+    /// scoring `name` and skipping the song when correct.
     ///
-    /// Users must be careful to `stop` removed tracks, so as to prevent
-    /// resource leaks.
-    pub fn modify_queue<F, O>(&self, func: F) -> O
-    where
-        F: FnOnce(&mut VecDeque<(Queued, MetaData)>) -> O,
-    {
-        let mut inner = self.inner.lock();
-        func(&mut inner.tracks)
-    }
-
-    /// Pause the track at the head of the queue.
-    pub fn pause(&self) -> TrackResult<()> {
-        let inner = self.inner.lock();
-
-        if let Some(handle) = inner.tracks.front() {
-            handle.0.pause()
-        } else {
-            Ok(())
+    /// Returns `None` when the message is not taken as an answer.
+    pub async fn answer(&self, user_id: UserId, channel_id: serenity::ChannelId, name: String, text: String) -> Option<Verdict> {
+        self.request(|reply| Command::Answer { user_id, channel_id, name, text, reply }).await.flatten()
+    }
+
+    /// Records the pick of `user_id` on the menu with `custom_id`, scoring it when correct.
+    pub async fn pick(&self, custom_id: String, user_id: UserId, name: String, option: usize) -> Pick {
+        self.request(|reply| Command::Pick { custom_id, user_id, name, option, reply })
+            .await
+            .unwrap_or(Pick::Closed)
+    }
+
+    /// Keyword of the playing song with every letter but the first hidden.
+    pub async fn hint(&self) -> Option<String> {
+        self.request(|reply| Command::Hint { reply }).await.flatten()
+    }
+
+    pub fn add_players(&self, names: Vec<String>) {
+        self.send(Command::AddPlayers { names });
+    }
+
+    /// Puts a user who joined the voice channel in the smallest team, when auto-balancing.
+    pub fn join_smallest_team(&self, user_id: UserId, name: String) {
+        self.send(Command::JoinSmallestTeam { user_id, name });
+    }
+
+    /// Adds a team, returns `false` if one with the same name exists.
+    pub async fn create_team(&self, name: String, answer_channel: Option<serenity::ChannelId>) -> bool {
+        self.request(|reply| Command::CreateTeam { name, answer_channel, reply }).await.unwrap_or(false)
+    }
+
+    /// Moves `user_id` to the team named `team`, returns `false` if there is no such team.
+    pub async fn add_to_team(&self, team: String, user_id: UserId, name: String) -> bool {
+        self.request(|reply| Command::AddToTeam { team, user_id, name, reply }).await.unwrap_or(false)
+    }
+
+    /// Splits `users` randomly into the teams, making `count` teams named by `team_name` first if there are none.
+    pub fn balance_teams(&self, count: usize, users: Vec<(UserId, String)>, team_name: impl Fn(usize) -> String + Send + 'static) {
+        self.send(Command::BalanceTeams { count, users, team_name: Box::new(team_name) });
+    }
+
+    pub fn clear_teams(&self) {
+        self.send(Command::ClearTeams);
+    }
+
+    pub async fn team_names(&self) -> Vec<String> {
+        self.request(|reply| Command::TeamNames { reply }).await.unwrap_or_default()
+    }
+
+    #[cfg(any(feature = "admin-api", test))]
+    pub async fn scores(&self) -> HashMap<String, i32> {
+        self.request(|reply| Command::Scores { reply }).await.unwrap_or_default()
+    }
+
+    pub fn restore_scores(&self, scores: HashMap<String, i32>) {
+        self.send(Command::RestoreScores { scores });
+    }
+}
+
+impl GuildSession {
+    /// Handles commands until the session ends or every handle is dropped.
+    async fn run(mut self, mut receiver: UnboundedReceiver<Command>) {
+        while let Some(command) = receiver.recv().await {
+            if let Command::End { reply } = command {
+                self.stop().await;
+                self.board.finish().await;
+                respond(reply, ());
+                break;
+            }
+            self.handle(command).await;
         }
+        info!("Session of {} is over", self.guild_id);
     }
 
-    /// Resume the track at the head of the queue.
-    pub fn resume(&self) -> TrackResult<()> {
-        let inner = self.inner.lock();
+    async fn handle(&mut self, command: Command) {
+        match command {
+            Command::TrackEnded { track, errored } => self.track_ended(&track, errored).await,
+            Command::TrackFailed { track, error, retry } => self.track_failed(&track, &error, retry).await,
+            Command::Preload => {
+                if let Some(track) = self.tracks.get(1) {
+                    // This is the sync-version so that we can fire and ignore
+                    // the request ASAP.
+                    drop(track.0.make_playable());
+                }
+            },
+            Command::Requeue { interval, interval_meta, track, meta } => {
+                self.requeue((interval, *interval_meta), track, *meta).await;
+            },
+            Command::RetryFailed => self.board.notify_key("track.retry_failed").await,
 
-        if let Some(handle) = inner.tracks.front() {
-            handle.0.play()
-        } else {
-            Ok(())
+            Command::Enqueue { interval, interval_meta, track, meta, with_interval, reply } => {
+                self.enqueue((interval, *interval_meta), track, *meta, with_interval).await;
+                respond(reply, ());
+            },
+            Command::Current { reply } => {
+                respond(reply, self.tracks.front().map(|(queued, meta)| (queued.handle(), meta.clone())));
+            },
+            Command::Queue { reply } => {
+                respond(reply, self.tracks.iter().map(|(queued, meta)| (queued.handle(), meta.clone())).collect());
+            },
+            Command::RemoveSong { index, reply } => {
+                let removed = self.remove_song(index).await;
+                if removed.is_some() {
+                    self.queue_changed().await;
+                }
+                respond(reply, removed);
+            },
+            Command::Skip { reply } => respond(reply, self.skip()),
+            Command::TogglePause { reply } => {
+                let result = self.toggle_pause().await;
+                respond(reply, result);
+            },
+            Command::VoteSkip { user_id, listeners, reply } => {
+                // more than half of the listeners
+                let result = if self.board.vote_skip(user_id) * 2 > listeners { self.skip() } else { Ok(()) };
+                respond(reply, result);
+            },
+            Command::Stop => self.stop().await,
+            // handled by `run`
+            Command::End { .. } => {},
+
+            Command::RegisterChannel { channel, reply } => {
+                let result = self.register_channel(&channel).await;
+                respond(reply, result);
+            },
+            Command::RegisterChannelIfEmpty { channel_id, reply } => {
+                self.register_channel_if_empty(channel_id).await;
+                respond(reply, ());
+            },
+            Command::Channel { reply } => respond(reply, self.channel),
+            Command::ClaimHost { user_id } => {
+                self.host.get_or_insert(user_id);
+            },
+            Command::Host { reply } => respond(reply, self.host),
+            Command::SavedSession { reply } => respond(reply, self.saved_session()),
+            Command::Notify { key, reply } => {
+                self.board.notify_key(key).await;
+                respond(reply, ());
+            },
+
+            Command::Answer { user_id, channel_id, name, text, reply } => {
+                let verdict = self.answer(user_id, channel_id, &name, &text).await;
+                respond(reply, verdict);
+            },
+            Command::Pick { custom_id, user_id, name, option, reply } => {
+                let pick = self.pick(&custom_id, user_id, name, option).await;
+                respond(reply, pick);
+            },
+            Command::Hint { reply } => respond(reply, self.board.hint()),
+            Command::AddPlayers { names } => {
+                for name in names {
+                    self.session.game.add_player(name);
+                }
+                self.apply_events(None).await;
+            },
+            Command::JoinSmallestTeam { user_id, name } => {
+                self.session.game.join_smallest_team(user_id, name);
+                self.apply_events(None).await;
+            },
+            Command::CreateTeam { name, answer_channel, reply } => {
+                let created = self.create_team(name, answer_channel).await;
+                respond(reply, created);
+            },
+            Command::AddToTeam { team, user_id, name, reply } => {
+                let added = self.session.game.add_to_team(&team, user_id, name);
+                self.apply_events(None).await;
+                respond(reply, added);
+            },
+            Command::BalanceTeams { count, users, team_name } => {
+                self.session.game.balance_teams(count, users, team_name, &mut rand::thread_rng());
+                self.apply_events(None).await;
+            },
+            Command::ClearTeams => {
+                self.session.game.clear_teams();
+                self.answer_channels.clear();
+                self.apply_events(None).await;
+            },
+            Command::TeamNames { reply } => respond(reply, self.session.game.team_names()),
+            #[cfg(any(feature = "admin-api", test))]
+            Command::Scores { reply } => respond(reply, self.session.game.scores()),
+            Command::RestoreScores { scores } => {
+                self.session.game.restore_scores(scores);
+                self.apply_events(None).await;
+            },
         }
     }
 
-    /// Stop the currently playing track, and clears the queue.
-    pub fn stop(&self) {
-        let mut inner = self.inner.lock();
+    /// Shows what changed in the game on the board, summing up `ended` if its round ended.
+    ///
+    /// The board announces the start of rounds and the end of the queue itself.
+    async fn apply_events(&mut self, ended: Option<&MetaData>) {
+        let mut scores_changed = false;
+        for event in self.session.game.drain_events() {
+            match event {
+                GameEvent::ScoresChanged => scores_changed = true,
+                GameEvent::Guessed(guess) => {
+                    if guess.verdict == Verdict::Correct {
+                        metrics::correct_guess();
+                    }
+                },
+                GameEvent::RoundEnded(round) => {
+                    self.board.close_round().await;
+                    if let Some(meta) = ended {
+                        self.board.sum_up(meta, &round).await;
+                    }
+                },
+                GameEvent::RoundStarted { .. } | GameEvent::Finished => {},
+            }
+        }
+        if scores_changed {
+            self.board.show_scores(self.session.game.score_text());
+        }
+    }
 
-        for track in inner.tracks.drain(..) {
-            // Errors when removing tracks don't really make
-            // a difference: an error just implies it's already gone.
-            drop(track.0.stop());
+    async fn track_ended(&mut self, track: &TrackHandle, errored: bool) {
+        // Due to possibility that users might remove, reorder,
+        // or dequeue+stop tracks, we need to verify that the FIRST
+        // track is the one who has ended.
+        if self.tracks.front().map(|(queued, _)| queued.uuid()) != Some(track.uuid()) {
+            return;
         }
+
+        // a song which failed to play has no round to sum up, nobody wins or loses points
+        let ended = self.tracks.pop_front().map(|(_, meta)| meta).filter(|_| !errored);
+        self.session.next_song();
+
+        info!("Queued track ended: {:?}.", track.uuid());
+        info!("{} tracks remain.", self.tracks.len());
+        self.next_track(ended).await;
     }
 
-    /// Skip to the next track in the queue, if it exists.
-    pub fn skip(&self) -> TrackResult<()> {
-        let inner = self.inner.lock();
+    /// Plays the head of the queue after the session moved on from the song `ended`.
+    async fn next_track(&mut self, ended: Option<MetaData>) {
+        self.apply_events(ended.as_ref()).await;
 
-        inner.stop_current()
+        // Keep going until we find one track which works, or we run out.
+        let meta = loop {
+            match self.tracks.front() {
+                Some(new) => {
+                    if new.0.play().is_err() {
+                        // Discard files which cannot be used for whatever reason.
+                        warn!("Track in Queue couldn't be played...");
+                        self.tracks.pop_front();
+                        self.session.next_song();
+                        self.apply_events(None).await;
+                    } else {
+                        break Some(new.1.clone());
+                    }
+                },
+                None => break None,
+            }
+        };
+        self.board.start_round(meta.as_ref(), self.answers()).await;
+        self.board.edit_status(meta);
+        self.queue_changed().await;
     }
 
-    /// Returns a list of currently queued tracks.
+    /// Reports a track which failed to play and prepares it once more if `retry`.
     ///
-    /// Does not allow for modification of the queue, instead returns a snapshot of the queue at the time of calling.
-    ///
-    /// Use [`modify_queue`] for direct modification of the queue.
+    /// Its end follows, which moves the queue on meanwhile.
+    async fn track_failed(&mut self, track: &TrackHandle, error: &str, retry: bool) {
+        let meta = self.tracks.iter()
+            .find(|(queued, _)| queued.uuid() == track.uuid())
+            .map(|(_, meta)| meta.clone());
+        warn!(
+            guild = %self.guild_id,
+            title = ?meta.as_ref().and_then(|meta| meta.title.clone()),
+            error,
+            "Track failed to play"
+        );
+        // the interval is not worth a notice
+        let Some(source) = meta.and_then(|meta| meta.source) else {
+            return;
+        };
+
+        match (retry, self.sender.upgrade()) {
+            (true, Some(sender)) => {
+                self.board.notify_key("track.failed_retry").await;
+                tokio::spawn(prepare_again(self.guild_id, source, self.voice.clone(), sender));
+            },
+            _ => self.board.notify_key("track.failed_skip").await,
+        }
+    }
+
+    /// Plays a song which was prepared again next, keeping songs and intervals alternating.
     ///
-    /// [`modify_queue`]: TrackQueue::modify_queue
-    #[must_use]
-    pub fn current_queue(&self) -> Vec<(TrackHandle, MetaData)> {
-        let inner = self.inner.lock();
+    /// While an interval plays, the song follows it and its own interval goes in front of the next song.
+    /// While a song plays, the song follows its own interval. With nothing left, it is queued as a new song.
+    async fn requeue(&mut self, interval: (TrackHandle, MetaData), track: TrackHandle, meta: MetaData) {
+        let Some((_, head)) = self.tracks.front() else {
+            self.enqueue(interval, track, meta, true).await;
+            return;
+        };
+        let head_is_interval = head.source.is_none();
+
+        let (interval, interval_meta) = interval;
+        let song = (Queued(track), meta);
+        let interval = (Queued(interval), interval_meta);
+        let mut inserted = if head_is_interval { vec![song, interval] } else { vec![interval, song] };
+        // no interval after the last song
+        if head_is_interval && self.tracks.len() == 1 {
+            if let Some((interval, _)) = inserted.pop() {
+                drop(interval.stop());
+            }
+        }
+
+        // the queue of the session starts after the head, as `tracks` does
+        for (queued, meta) in inserted.into_iter().rev() {
+            self.session.enqueue_next(meta.song());
+            self.tracks.insert(1, (queued, meta));
+        }
+        self.queue_changed().await;
+    }
+
+    async fn enqueue(&mut self, interval: (TrackHandle, MetaData), track: TrackHandle, meta: MetaData, with_interval: bool) {
+        let (interval, interval_meta) = interval;
+        let was_empty = self.tracks.is_empty();
+        self.session.enqueue(interval_meta.song());
+        self.session.enqueue(meta.song());
+        self.tracks.push_back((Queued(interval.clone()), interval_meta));
+        self.tracks.push_back((Queued(track.clone()), meta));
+        // the interval is at the head now, as the playing song of the session
+        if was_empty {
+            self.session.next_song();
+            self.apply_events(None).await;
+        }
 
-        inner.tracks.iter().map(|e| (e.0.handle(), e.1.clone())).collect()
+        let (should_play, handle) = if with_interval {
+            (self.tracks.len() <= 2, interval)
+        } else {
+            (self.tracks.len() == 1, track)
+        };
+        if should_play {
+            drop(handle.play());
+        }
+        self.queue_changed().await;
     }
 
-    pub fn channel(&self) -> Option<ChannelId> {
-        let inner = self.inner.lock();
-        inner.channel.clone()
+    // the queue of the session starts after the head of `tracks`
+    async fn remove_song(&mut self, index: usize) -> Option<MetaData> {
+        if index == 0 || self.tracks.get(index)?.1.source.is_none() {
+            return None;
+        }
+        let (song, meta) = self.tracks.remove(index)?;
+        self.session.remove(index - 1);
+        drop(song.stop());
+        if index > 1 && self.tracks.get(index - 1).is_some_and(|(_, meta)| meta.source.is_none()) {
+            if let Some((interval, _)) = self.tracks.remove(index - 1) {
+                self.session.remove(index - 2);
+                drop(interval.stop());
+            }
+        }
+        Some(meta)
     }
 
-}
+    /// Pauses or resumes the track at the head of the queue, showing the time left on the board.
+    async fn toggle_pause(&mut self) -> TrackResult<()> {
+        let Some((queued, meta)) = self.tracks.front() else {
+            return Ok(());
+        };
+        let info = queued.get_info().await?;
+        let remaining = meta.duration.unwrap_or(0) - info.play_time.as_secs() as i64;
+
+        let paused = !self.board.is_paused();
+        if paused {
+            queued.pause()?;
+        } else {
+            queued.play()?;
+        }
+        self.board.set_paused(paused, remaining);
+        Ok(())
+    }
 
-impl GuildQueueCore {
     /// Skip to the next track in the queue, if it exists.
-    fn stop_current(&self) -> TrackResult<()> {
+    fn skip(&self) -> TrackResult<()> {
         if let Some(handle) = self.tracks.front() {
             handle.0.stop()
         } else {
             Ok(())
         }
     }
-}
\ No newline at end of file
+
+    async fn stop(&mut self) {
+        for track in self.tracks.drain(..) {
+            // Errors when removing tracks don't really make
+            // a difference: an error just implies it's already gone.
+            drop(track.0.stop());
+        }
+        self.session.clear();
+        self.apply_events(None).await;
+    }
+
+    async fn register_channel(&mut self, channel: &GuildChannel) -> serenity::Result<()> {
+        self.channel = Some(channel.id.into());
+        self.board.set(channel).await
+    }
+
+    async fn register_channel_if_empty(&mut self, channel_id: serenity::ChannelId) {
+        if self.channel.is_some() {
+            return;
+        }
+
+        match channel_id.to_channel(&self.http).await {
+            Ok(Channel::Guild(channel)) if channel.kind == ChannelType::Text => {
+                info!("No channel registered, registering {}", channel.name);
+                if let Err(why) = self.register_channel(&channel).await {
+                    warn!(error = ?why, "Failed to register {}", channel.name);
+                }
+            },
+            _ => warn!("Channel {} can not be registered as board", channel_id),
+        }
+    }
+
+    async fn answer(&mut self, user_id: UserId, channel_id: serenity::ChannelId, name: &str, text: &str) -> Option<Verdict> {
+        // members of a team with its own answer channel answer only there
+        let in_answer_channel = match self.answer_channel(user_id) {
+            Some(team_channel) => team_channel == channel_id,
+            None => self.channel.is_some_and(|channel| channel == channel_id.into()),
+        };
+
+        // answers are picked from the menu while a multiple-choice round is open
+        if !in_answer_channel || !self.board.takes_typed_answers() {
+            return None;
+        }
+        let verdict = self.session.guess(user_id, name, text)?;
+        if verdict == Verdict::Correct {
+            // the session moved on to the next song, the track follows
+            let ended = self.tracks.pop_front().map(|(queued, meta)| {
+                drop(queued.stop());
+                meta
+            });
+            self.next_track(ended).await;
+        } else {
+            self.apply_events(None).await;
+        }
+        Some(verdict)
+    }
+
+    async fn pick(&mut self, custom_id: &str, user_id: UserId, name: String, option: usize) -> Pick {
+        let pick = self.board.pick(custom_id, user_id, option);
+        let verdict = match pick {
+            Pick::Correct => Verdict::Correct,
+            Pick::Wrong => Verdict::Wrong,
+            Pick::AlreadyPicked | Pick::Closed => return pick,
+        };
+        let text = self.board.choice(option).unwrap_or_default();
+        self.session.game.record(user_id, name.clone(), text, verdict);
+        if pick == Pick::Correct {
+            self.session.game.score(user_id, &name);
+            if guild_settings::get(self.guild_id).answer_mode == AnswerMode::FirstPick {
+                self.board.close_round().await;
+                drop(self.skip());
+            }
+        }
+        self.apply_events(None).await;
+        pick
+    }
+
+    /// Adds a team, returns `false` if one with the same name exists.
+    async fn create_team(&mut self, name: String, answer_channel: Option<serenity::ChannelId>) -> bool {
+        if !self.session.game.create_team(name.clone()) {
+            return false;
+        }
+        if let Some(channel) = answer_channel {
+            self.answer_channels.insert(name, channel);
+        }
+        self.apply_events(None).await;
+        true
+    }
+
+    /// Answer channel of the team of `user_id`, if the team has one.
+    fn answer_channel(&self, user_id: UserId) -> Option<serenity::ChannelId> {
+        let team = self.session.game.team_of(&user_id)?;
+        self.answer_channels.get(team).copied()
+    }
+
+    fn saved_session(&self) -> SavedSession {
+        SavedSession {
+            channel: self.channel.map(|channel| channel.0.get()),
+            score: self.session.game.scores(),
+            // the interval between tracks is not included
+            tracks: self.tracks.iter().filter_map(|(_, meta)| meta.source.clone()).collect(),
+            host: self.host.map(UserId::get),
+        }
+    }
+
+    /// Shows the number of queued songs on the board and saves the session,
+    /// so that it can be resumed after a restart.
+    async fn queue_changed(&mut self) {
+        self.board.set_queue_len(self.queued_songs());
+        if let Err(why) = session_store::save(self.guild_id, &self.saved_session()).await {
+            warn!("Failed to save session of {}: {:?}", self.guild_id, why);
+        }
+    }
+
+    /// Number of songs waiting after the playing one.
+    fn queued_songs(&self) -> usize {
+        self.tracks.iter().skip(1).filter(|(_, meta)| meta.source.is_some()).count()
+    }
+
+    /// Answers of every queued song, see [`MetaData::answer`].
+    fn answers(&self) -> Vec<String> {
+        self.tracks.iter().filter_map(|(_, meta)| meta.answer()).collect()
+    }
+}
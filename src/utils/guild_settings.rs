@@ -0,0 +1,101 @@
+use poise::serenity_prelude as serenity;
+use serenity::{GuildId, RoleId};
+
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{config, i18n::Lang};
+
+/// How players answer the playing song.
+#[derive(poise::ChoiceParameter, Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnswerMode {
+    /// Typing the keyword in the registered channel.
+    #[default]
+    #[name = "typing"]
+    #[name_localized("ko", "직접 입력")]
+    Typing,
+    /// Picking from a menu, the first correct pick scores and skips the song.
+    #[name = "first pick"]
+    #[name_localized("ko", "객관식 (선착순)")]
+    FirstPick,
+    /// Picking from a menu, everyone picking correctly before the song ends scores.
+    #[name = "every pick"]
+    #[name_localized("ko", "객관식 (전원)")]
+    EveryPick,
+}
+
+/// Per-guild preferences set by commands.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct GuildSettings {
+    #[serde(default)]
+    pub language: Lang,
+    /// Members with this role may control any game.
+    #[serde(default)]
+    pub host_role: Option<RoleId>,
+    /// Members with this role may add and skip songs.
+    /// Everyone may when it is not set.
+    #[serde(default)]
+    pub dj_role: Option<RoleId>,
+    #[serde(default)]
+    pub answer_mode: AnswerMode,
+}
+
+lazy_static! {
+    static ref SETTINGS: DashMap<GuildId, GuildSettings> = DashMap::new();
+}
+
+fn settings_path() -> PathBuf {
+    config::get().data_dir.join("settings.json")
+}
+
+/// Returns the settings of the guild, or the defaults.
+pub fn get(guild_id: GuildId) -> GuildSettings {
+    SETTINGS.get(&guild_id).map(|settings| settings.clone()).unwrap_or_default()
+}
+
+/// Changes the settings of the guild and writes every setting to disk.
+pub async fn update(guild_id: GuildId, func: impl FnOnce(&mut GuildSettings)) {
+    func(&mut SETTINGS.entry(guild_id).or_default());
+    save().await;
+}
+
+async fn save() {
+    let settings = SETTINGS.iter()
+        .map(|entry| (entry.key().get(), entry.value().clone()))
+        .collect::<HashMap<u64, GuildSettings>>();
+    let json = match serde_json::to_vec_pretty(&settings) {
+        Ok(json) => json,
+        Err(why) => {
+            warn!("Failed to serialize settings: {:?}", why);
+            return;
+        }
+    };
+    let data_dir = &config::get().data_dir;
+    if let Err(why) = tokio::fs::create_dir_all(data_dir).await {
+        warn!("Failed to create {:?}: {:?}", data_dir, why);
+        return;
+    }
+    if let Err(why) = tokio::fs::write(settings_path(), json).await {
+        warn!("Failed to save settings: {:?}", why);
+    }
+}
+
+/// Loads the settings saved by previous runs, replacing the ones in memory.
+pub async fn load() {
+    let Ok(json) = tokio::fs::read(settings_path()).await else {
+        return;
+    };
+    match serde_json::from_slice::<HashMap<u64, GuildSettings>>(&json) {
+        Ok(settings) => {
+            SETTINGS.clear();
+            for (guild_id, settings) in settings.into_iter().filter(|(id, _)| *id != 0) {
+                SETTINGS.insert(GuildId::new(guild_id), settings);
+            }
+        },
+        Err(why) => warn!("Broken settings file: {:?}", why),
+    }
+}
@@ -0,0 +1,96 @@
+use poise::serenity_prelude as serenity;
+use serenity::{Context, GuildId, UserId};
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use tracing::info;
+use std::{collections::HashSet, time::Duration};
+
+use crate::{
+    config,
+    connection_handler::end_session,
+    utils::guild_queue::find_guild_queue,
+};
+
+lazy_static! {
+    // guilds watched by a supervisor
+    static ref SUPERVISED: Mutex<HashSet<GuildId>> = Mutex::new(HashSet::new());
+}
+
+/// Spawns a task which ends the voice session of the guild when the queue has been
+/// empty for `idle_timeout` or the bot is alone in the channel.
+///
+/// A guild without a session counts as an empty queue. Only one supervisor runs per guild,
+/// until the bot leaves its voice channel.
+pub fn spawn_idle_supervisor(ctx: Context, guild_id: GuildId) {
+    if !SUPERVISED.lock().insert(guild_id) {
+        return;
+    }
+
+    let idle_timeout = Duration::from_secs(config::get().idle_timeout);
+    let check_interval = Duration::from_secs(config::get().idle_check_interval);
+    tokio::spawn(async move {
+        let mut idle = Duration::ZERO;
+        loop {
+            tokio::time::sleep(check_interval).await;
+
+            if !is_connected(&ctx, guild_id).await {
+                break;
+            }
+
+            if is_bot_alone(&ctx, guild_id).await {
+                info!("Bot is alone in voice channel of {}, leaving", guild_id);
+                drop(end_session(&ctx, guild_id).await);
+                break;
+            }
+
+            let empty = match find_guild_queue(&ctx, guild_id).await {
+                Some(guild_queue) => guild_queue.is_empty().await,
+                None => true,
+            };
+            idle = if empty { idle + check_interval } else { Duration::ZERO };
+            if idle >= idle_timeout {
+                info!("Queue of {} has been empty for {:?}, leaving", guild_id, idle);
+                drop(end_session(&ctx, guild_id).await);
+                break;
+            }
+        }
+        SUPERVISED.lock().remove(&guild_id);
+    });
+}
+
+async fn is_connected(ctx: &Context, guild_id: GuildId) -> bool {
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.current_channel().is_some(),
+        None => false,
+    }
+}
+
+/// Returns whether the bot is connected and no other user is in its voice channel.
+pub async fn is_bot_alone(ctx: &Context, guild_id: GuildId) -> bool {
+    listeners(ctx, guild_id).await.is_some_and(|listeners| listeners.is_empty())
+}
+
+/// Returns the users other than bots in the voice channel of the bot,
+/// or `None` if the bot is not connected.
+pub async fn listeners(ctx: &Context, guild_id: GuildId) -> Option<Vec<UserId>> {
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let bot_channel = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.current_channel(),
+        None => None,
+    };
+    let bot_channel = bot_channel?;
+
+    let bot_id = ctx.cache.current_user().id;
+    // CacheRef is not Send
+    let guild = guild_id.to_guild_cached(&ctx.cache)?;
+    let listeners = guild.voice_states
+        .values()
+        .filter(|voice_state| voice_state.channel_id.map(Into::into) == Some(bot_channel))
+        .filter(|voice_state| voice_state.user_id != bot_id
+            && !voice_state.member.as_ref().is_some_and(|member| member.user.bot))
+        .map(|voice_state| voice_state.user_id)
+        .collect();
+    Some(listeners)
+}
@@ -0,0 +1,122 @@
+//! Probe of the external tools the media pipeline runs.
+//!
+//! [`probe`] runs once at startup so that a missing tool is reported before anyone plays a song.
+//! When the bot keeps running without them, the [`ready`] check disables the commands which need them.
+
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use std::io::ErrorKind;
+use tokio::process::Command;
+
+use crate::config;
+
+/// What probing one tool found.
+#[derive(Debug, Clone)]
+pub struct Tool {
+    pub name: &'static str,
+    /// Executable as configured.
+    pub command: String,
+    /// Version it reported, or why it could not be run.
+    pub version: Result<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub tools: Vec<Tool>,
+    /// Whether ffmpeg can encode opus, which every clip is cut to.
+    pub libopus: bool,
+    /// Seconds the silent track between songs lasts, `None` when ffprobe could not read it.
+    pub interval: Option<i64>,
+}
+
+impl Report {
+    /// What stops the media pipeline, each with what to do about it.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = self.tools.iter()
+            .filter_map(|tool| tool.version.as_ref().err().map(|why| format!(
+                "{} ({}) cannot be run: {}. Install it or set `{}` in the configuration.",
+                tool.name, tool.command, why, tool.name.replace('-', "_"),
+            )))
+            .collect::<Vec<String>>();
+        let ffmpeg_runs = self.tools.iter().any(|tool| tool.name == "ffmpeg" && tool.version.is_ok());
+        if ffmpeg_runs && !self.libopus {
+            problems.push(
+                "ffmpeg has no libopus encoder. Install an ffmpeg built with --enable-libopus \
+                or set `ffmpeg` in the configuration to one.".to_owned()
+            );
+        }
+        problems
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.problems().is_empty()
+    }
+}
+
+lazy_static! {
+    static ref REPORT: RwLock<Option<Report>> = RwLock::new(None);
+}
+
+/// Runs every tool for its version and checks the codecs, keeping the result for [`ready`].
+pub async fn probe() -> Report {
+    let config = config::get();
+    let tools = vec![
+        Tool { name: "yt-dlp", command: config.yt_dlp.clone(), version: version(&config.yt_dlp, "--version").await },
+        Tool { name: "ffmpeg", command: config.ffmpeg.clone(), version: version(&config.ffmpeg, "-version").await },
+        Tool { name: "ffprobe", command: config.ffprobe.clone(), version: version(&config.ffprobe, "-version").await },
+    ];
+    let libopus = match run(&config.ffmpeg, &["-hide_banner", "-encoders"]).await {
+        Ok(encoders) => encoders.lines().any(|line| line.split_whitespace().nth(1) == Some("libopus")),
+        Err(_) => false,
+    };
+
+    let interval = match run(&config.ffprobe, &[
+        "-i", &config.no_sound.to_string_lossy(),
+        "-show_entries", "format=duration",
+        "-v", "quiet",
+        "-of", "csv=p=0",
+    ]).await {
+        Ok(duration) => duration.trim().parse::<f64>().ok().map(|duration| duration as i64),
+        Err(_) => None,
+    };
+
+    let report = Report { tools, libopus, interval };
+    *REPORT.write() = Some(report.clone());
+    report
+}
+
+/// Whether the media pipeline can run, assumed before the first probe.
+pub fn ready() -> bool {
+    REPORT.read().as_ref().is_none_or(Report::is_ready)
+}
+
+/// Length of the silent track between songs found by the last probe, unknown before it or when it failed.
+pub fn interval() -> Option<i64> {
+    REPORT.read().as_ref().and_then(|report| report.interval)
+}
+
+/// First line of what `command flag` prints, without the tool name and copyright of ffmpeg.
+async fn version(command: &str, flag: &str) -> Result<String, String> {
+    let output = run(command, &[flag]).await?;
+    let line = output.lines().next().unwrap_or_default();
+    let version = match line.split_once(" version ") {
+        Some((_, rest)) => rest.split_whitespace().next().unwrap_or_default(),
+        None => line.trim(),
+    };
+    Ok(version.to_owned())
+}
+
+async fn run(command: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(command)
+        .args(args)
+        .output()
+        .await
+        .map_err(|why| match why.kind() {
+            ErrorKind::NotFound => "not found".to_owned(),
+            _ => why.to_string(),
+        })?;
+    if !output.status.success() {
+        return Err(format!("exited with {}", output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -1,4 +1,14 @@
 pub mod url_checker;
 pub mod youtube_dl;
 pub mod guild_queue;
-pub mod board;
\ No newline at end of file
+pub mod board;
+pub mod idle_supervisor;
+pub mod session_store;
+pub mod guild_settings;
+pub mod permission;
+pub mod choice_round;
+pub mod board_renderer;
+pub mod media_tools;
+// only the dashboard edits quiz sets
+#[cfg_attr(not(feature = "dashboard"), allow(dead_code))]
+pub mod quiz_store;
\ No newline at end of file
@@ -0,0 +1,46 @@
+use poise::serenity_prelude as serenity;
+use serenity::{GuildId, Member, Permissions, RoleId, UserId};
+
+use super::guild_settings;
+
+/// What a command or control needs from the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Adding, skipping and removing songs.
+    Dj,
+    /// Controlling or ending the running game.
+    Host,
+}
+
+impl Level {
+    /// Catalog key of the message shown when the level is not met.
+    pub fn denied_key(&self) -> &'static str {
+        match self {
+            Level::Dj => "permission.dj_required",
+            Level::Host => "permission.host_required",
+        }
+    }
+}
+
+/// Returns whether `member` meets `level` in the guild.
+///
+/// `host` is the user who started the running game, if any.
+/// Members who can manage the guild meet every level, hosts meet the DJ level as well.
+/// While no game is running anyone is a host, so that anyone can start one.
+pub fn allows(guild_id: GuildId, member: &Member, host: Option<UserId>, level: Level) -> bool {
+    if member.permissions.is_some_and(|permissions| permissions.contains(Permissions::MANAGE_GUILD)) {
+        return true;
+    }
+
+    let settings = guild_settings::get(guild_id);
+    let has_role = |role: Option<RoleId>| role.is_some_and(|role| member.roles.contains(&role));
+    let is_host = match host {
+        Some(host) => host == member.user.id || has_role(settings.host_role),
+        None => true,
+    };
+
+    match level {
+        Level::Host => is_host,
+        Level::Dj => is_host || settings.dj_role.is_none() || has_role(settings.dj_role),
+    }
+}
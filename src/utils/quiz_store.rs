@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::{config, utils::{url_checker::YOUTUBE_PREFIX, youtube_dl::TrackSource}};
+
+/// A song of a quiz set, the same as a row of a `/playlist` csv.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QuizEntry {
+    // video id, as returned by `url_checker`
+    pub url: String,
+    // title and length of the video, filled from yt-dlp
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub length: Option<i64>,
+    #[serde(default)]
+    pub start: i64,
+    #[serde(default)]
+    pub duration: i64,
+    #[serde(default)]
+    pub keyword: Option<String>,
+}
+
+impl QuizEntry {
+    pub fn source(&self) -> TrackSource {
+        TrackSource {
+            url: self.url.clone(),
+            start: self.start,
+            duration: self.duration,
+            keyword: self.keyword.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct QuizSet {
+    pub id: u64,
+    pub name: String,
+    pub entries: Vec<QuizEntry>,
+}
+
+impl QuizSet {
+    /// Rows of a csv which `/playlist` accepts.
+    pub fn to_csv(&self) -> String {
+        self.entries.iter()
+            .map(|entry| {
+                let mut row = format!("{}{},{},{}", YOUTUBE_PREFIX, entry.url, entry.start, entry.duration);
+                if let Some(keyword) = &entry.keyword {
+                    row.push(',');
+                    row.push_str(keyword);
+                }
+                row
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn quiz_dir() -> PathBuf {
+    config::get().data_dir.join("quizzes")
+}
+
+fn quiz_path(id: u64) -> PathBuf {
+    quiz_dir().join(format!("{}.json", id))
+}
+
+/// Creates an empty quiz set, its id is the creation time in milliseconds.
+pub async fn create(name: String) -> std::io::Result<QuizSet> {
+    let mut id = chrono::Utc::now().timestamp_millis() as u64;
+    while tokio::fs::try_exists(quiz_path(id)).await? {
+        id += 1;
+    }
+    let set = QuizSet { id, name, entries: Vec::new() };
+    save(&set).await?;
+    Ok(set)
+}
+
+pub async fn save(set: &QuizSet) -> std::io::Result<()> {
+    tokio::fs::create_dir_all(quiz_dir()).await?;
+    let json = serde_json::to_vec_pretty(set)?;
+    tokio::fs::write(quiz_path(set.id), json).await
+}
+
+pub async fn load(id: u64) -> Option<QuizSet> {
+    let json = tokio::fs::read(quiz_path(id)).await.ok()?;
+    match serde_json::from_slice(&json) {
+        Ok(set) => Some(set),
+        Err(why) => {
+            warn!("Broken quiz set file {}: {:?}", id, why);
+            None
+        }
+    }
+}
+
+/// Every quiz set, oldest first.
+pub async fn load_all() -> Vec<QuizSet> {
+    let mut sets = Vec::new();
+    let Ok(mut entries) = tokio::fs::read_dir(quiz_dir()).await else {
+        return sets;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Some(id) = entry.path()
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        if let Some(set) = load(id).await {
+            sets.push(set);
+        }
+    }
+    sets.sort_by_key(|set| set.id);
+    sets
+}
+
+/// Answers of the songs in every quiz set, as they are judged once played.
+pub async fn answers() -> Vec<String> {
+    load_all().await
+        .into_iter()
+        .flat_map(|set| set.entries)
+        .filter_map(|entry| entry.keyword.or(entry.title))
+        .collect()
+}
+
+/// Returns whether the set existed.
+pub async fn remove(id: u64) -> bool {
+    tokio::fs::remove_file(quiz_path(id)).await.is_ok()
+}
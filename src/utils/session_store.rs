@@ -0,0 +1,71 @@
+use poise::serenity_prelude as serenity;
+use serenity::GuildId;
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+use tracing::warn;
+
+use crate::{config, utils::youtube_dl::TrackSource};
+
+/// State of a guild session which survives a restart.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct SavedSession {
+    // text channel the board was registered to
+    pub channel: Option<u64>,
+    pub score: HashMap<String, i32>,
+    pub tracks: Vec<TrackSource>,
+    // user who started the game, missing in sessions saved before it was kept
+    #[serde(default)]
+    pub host: Option<u64>,
+}
+
+fn session_dir() -> PathBuf {
+    config::get().data_dir.join("sessions")
+}
+
+fn session_path(guild_id: GuildId) -> PathBuf {
+    session_dir().join(format!("{}.json", guild_id))
+}
+
+pub async fn save(guild_id: GuildId, session: &SavedSession) -> std::io::Result<()> {
+    tokio::fs::create_dir_all(session_dir()).await?;
+    let json = serde_json::to_vec_pretty(session)?;
+    tokio::fs::write(session_path(guild_id), json).await
+}
+
+pub async fn load(guild_id: GuildId) -> Option<SavedSession> {
+    let json = tokio::fs::read(session_path(guild_id)).await.ok()?;
+    match serde_json::from_slice(&json) {
+        Ok(session) => Some(session),
+        Err(why) => {
+            warn!("Broken session file of {}: {:?}", guild_id, why);
+            None
+        }
+    }
+}
+
+pub async fn load_all() -> Vec<(GuildId, SavedSession)> {
+    let mut sessions = Vec::new();
+    let Ok(mut entries) = tokio::fs::read_dir(session_dir()).await else {
+        return sessions;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let guild_id = entry.path()
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<u64>().ok())
+            .filter(|&id| id != 0)
+            .map(GuildId::new);
+        if let Some(guild_id) = guild_id {
+            if let Some(session) = load(guild_id).await {
+                sessions.push((guild_id, session));
+            }
+        }
+    }
+    sessions
+}
+
+pub async fn remove(guild_id: GuildId) {
+    drop(tokio::fs::remove_file(session_path(guild_id)).await);
+}
@@ -1,16 +1,21 @@
 use songbird::input::{
-    AudioStream,
     AudioStreamError,
-    AuxMetadata,
     File,
+    Input,
 };
 
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use quiz_core::{clip, session::Song};
+use poise::serenity_prelude::async_trait;
 use serde::{Deserialize, Serialize};
-use std::io::ErrorKind;
-use tokio::process::Command;
+use std::{io::ErrorKind, sync::Arc, time::Instant};
+use tokio::{process::Command, sync::{OnceCell, Semaphore}};
+use tracing::warn;
 
-use crate::global::*;
+use crate::{config, metrics, utils::media_tools};
 
+// names of the tools in metrics and errors, the executables are configured
 const YOUTUBE_DL_COMMAND: &str = "yt-dlp";
 const FFMPEG_COMMAND: &str = "ffmpeg";
 
@@ -19,6 +24,55 @@ pub struct MetaData {
     pub duration: Option<i64>,
     pub title: Option<String>,
     pub keyword: Option<String>,
+    pub webpage_url: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub thumbnail: Option<String>,
+    pub year: Option<i32>,
+    // what the track was prepared from, `None` for the interval
+    pub source: Option<TrackSource>,
+}
+
+impl MetaData {
+    /// Metadata of the silent track played between songs, lasting `duration` seconds.
+    pub fn interval(duration: Option<i64>) -> Self {
+        Self {
+            duration,
+            title: Some("interval".to_owned()),
+            keyword: None,
+            webpage_url: None,
+            artist: None,
+            album: None,
+            thumbnail: None,
+            year: None,
+            source: None,
+        }
+    }
+
+    /// What players have to guess, the keyword or else the title.
+    /// `None` for the interval.
+    pub fn answer(&self) -> Option<String> {
+        self.source.as_ref()?;
+        self.keyword.clone().or_else(|| self.title.clone())
+    }
+
+    /// The song as the quiz session sees it.
+    pub fn song(&self) -> Song {
+        Song {
+            title: self.title.clone(),
+            answer: self.answer(),
+            duration: self.duration,
+        }
+    }
+}
+
+/// Describes how a track is prepared by `ytdl_optioned`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TrackSource {
+    pub url: String,
+    pub start: i64,
+    pub duration: i64,
+    pub keyword: Option<String>,
 }
 
 impl From<&Output> for MetaData {
@@ -27,17 +81,19 @@ impl From<&Output> for MetaData {
             duration: output.duration,
             title: output.title.clone(),
             keyword: None,
+            webpage_url: output.webpage_url.clone(),
+            artist: output.artist.clone(),
+            album: output.album.clone(),
+            thumbnail: output.thumbnail.clone(),
+            year: output.year(),
+            source: None,
         }
     }
 }
 
 impl From<Output> for MetaData {
     fn from(output: Output) -> Self {
-        MetaData {
-            duration: output.duration,
-            title: output.title,
-            keyword: None,
-        }
+        (&output).into()
     }
 }
 
@@ -52,30 +108,106 @@ pub struct Output {
     pub url: String,
     pub webpage_url: Option<String>,
     pub audio_ext: Option<String>,
+    pub thumbnail: Option<String>,
+    pub release_year: Option<i32>,
+    // YYYYMMDD
+    pub upload_date: Option<String>,
+}
+
+impl Output {
+    /// Release year of the track, or else the year it was uploaded.
+    fn year(&self) -> Option<i32> {
+        self.release_year.or_else(|| {
+            self.upload_date.as_ref()
+                .and_then(|date| date.get(..4))
+                .and_then(|year| year.parse().ok())
+        })
+    }
+}
+
+/// Turns a `TrackSource` into something to queue.
+///
+/// yt-dlp and ffmpeg unless replaced by [`set_backend`].
+#[async_trait]
+pub trait MediaBackend: Send + Sync {
+    async fn prepare(&self, source: &TrackSource) -> Result<(Input, MetaData), AudioStreamError>;
+    /// The silent track played between songs.
+    async fn interval(&self) -> Result<(Input, MetaData), AudioStreamError>;
+}
+
+struct YtDlp;
+
+#[async_trait]
+impl MediaBackend for YtDlp {
+    async fn prepare(&self, source: &TrackSource) -> Result<(Input, MetaData), AudioStreamError> {
+        let (path, output) = ytdl_optioned(&source.url, source.start, source.duration).await?;
+        let mut meta: MetaData = output.into();
+        meta.keyword = source.keyword.clone();
+        meta.source = Some(source.clone());
+        Ok((File::new(path).into(), meta))
+    }
+
+    async fn interval(&self) -> Result<(Input, MetaData), AudioStreamError> {
+        let config = config::get();
+        let audio = NO_SOUND
+            .get_or_try_init(|| tokio::fs::read(&config.no_sound))
+            .await
+            .map_err(|why| AudioStreamError::Fail(
+                format!("could not read {}: {}", config.no_sound.display(), why).into()
+            ))?;
+        // probed at startup, the board shows no progress for an unknown length
+        Ok((audio.as_slice().into(), MetaData::interval(media_tools::interval())))
+    }
+}
+
+lazy_static! {
+    static ref BACKEND: RwLock<Arc<dyn MediaBackend>> = RwLock::new(Arc::new(YtDlp));
+    // runs of yt-dlp and ffmpeg at a time, further ones wait
+    static ref DOWNLOADS: Semaphore = Semaphore::new(config::get().max_downloads);
+    // read on the first interval, so that a missing file only fails playback
+    static ref NO_SOUND: OnceCell<Vec<u8>> = OnceCell::new();
+}
+
+/// Replaces the media backend, so that tests run without yt-dlp and the network.
+#[cfg(test)]
+pub fn set_backend(backend: Arc<dyn MediaBackend>) {
+    *BACKEND.write() = backend;
+}
+
+/// Downloads and cuts the track described by `source`, ready to be queued.
+pub async fn prepare_source(source: &TrackSource) -> Result<(Input, MetaData), AudioStreamError> {
+    let backend = BACKEND.read().clone();
+    backend.prepare(source).await
+}
+
+/// The silent track to queue in front of a song.
+pub async fn prepare_interval() -> Result<(Input, MetaData), AudioStreamError> {
+    let backend = BACKEND.read().clone();
+    backend.interval().await
 }
 
 pub async fn ytdl_optioned(
     url: &String, start: i64, duration: i64
 ) -> Result<(String, Output), AudioStreamError> {
-    let mut ytdl_output = format!("{TARGET}{TMP}{url}.%(ext)s");
-    let ytdl_args = [
-        "-j",
-        "--no-simulate",
-        url,
-        "-f",
-        "ba[abr<=128][vcodec=none]/best",
-        "--no-playlist",
-        "-o",
-        ytdl_output.as_str()
-    ];
-
-    let mut ytdl = Command::new(YOUTUBE_DL_COMMAND)
+    let config = config::get();
+    let cache_path = |name: String| config.cache_dir.join(name).to_string_lossy().into_owned();
+    let ytdl_args = clip::download_args(url, &config.ytdl_format, &cache_path(format!("{url}.%(ext)s")));
+
+    let _permit = DOWNLOADS.acquire().await.expect("downloads are never closed");
+    metrics::media_cache(is_downloaded(url).await);
+    let started = Instant::now();
+    let ytdl = Command::new(&config.yt_dlp)
         .args(ytdl_args)
         .output()
-        .await
+        .await;
+    metrics::media_run(
+        YOUTUBE_DL_COMMAND,
+        started.elapsed(),
+        ytdl.as_ref().is_ok_and(|output| output.status.success()));
+    let mut ytdl = ytdl
         .map_err(|e| {
             AudioStreamError::Fail(if e.kind() == ErrorKind::NotFound {
-                format!("could not find executable '{}' on path", YOUTUBE_DL_COMMAND).into()
+                format!("could not find executable '{}' on path", config.yt_dlp).into()
             } else {
                 Box::new(e)
             })
@@ -103,53 +235,142 @@ pub async fn ytdl_optioned(
         .first()
         .ok_or_else(|| {
             AudioStreamError::Fail(format!("no results found for '{url}'").into())
-        }).unwrap().clone();
-    ytdl_output = format!("{TARGET}{TMP}{url}.{}", meta.audio_ext.as_ref().unwrap());
-
-    let output_full = format!("{TARGET}{TMP}{url}_full.{}", meta.audio_ext.as_ref().unwrap());
-    let mut ffmpeg_args = vec![
-        "-y".to_owned(),
-        "-i".to_owned(),
-        ytdl_output,
-        "-c:a".to_owned(),
-        "libopus".to_owned(),
-        output_full.clone(),
-    ];
+        })?.clone();
+    let audio_ext = meta.audio_ext.clone().ok_or_else(|| {
+        AudioStreamError::Fail(format!("no audio extension for '{url}'").into())
+    })?;
+    let ytdl_output = cache_path(format!("{url}.{audio_ext}"));
+
+    // the whole song is kept beside the clip
+    let output_full = cache_path(format!("{url}_full.{audio_ext}"));
+    let mut ffmpeg_args = clip::input_args(&ytdl_output);
+    ffmpeg_args.extend(clip::output_args(&output_full, 0, None));
     let mut ffmpeg_output = output_full;
 
     if start != 0 || duration != 0 {
-        let start = start.to_string();
-        let duration = if duration == 0 {
-            meta.duration.unwrap().to_string()
-        } else {
-            meta.duration = Some(duration);
-            duration.to_string()
-        };
-        
-        let output_cut = format!("{TARGET}{TMP}{url}_cut.{}", meta.audio_ext.as_ref().unwrap());
-        ffmpeg_args.extend_from_slice(&[
-            "-ss".to_owned(),
-            start,
-            "-t".to_owned(),
-            duration,
-            "-c:a".to_owned(),
-            "libopus".to_owned(),
-            output_cut.clone(),
-        ]);
+        let length = clip::length(duration, meta.duration);
+        meta.duration = length;
+        let output_cut = cache_path(format!("{url}_cut.{audio_ext}"));
+        ffmpeg_args.extend(clip::output_args(&output_cut, start, length));
         ffmpeg_output = output_cut;
     }
-    Command::new(FFMPEG_COMMAND)
+    let started = Instant::now();
+    let ffmpeg = Command::new(&config.ffmpeg)
         .args(ffmpeg_args)
         .output()
-        .await
+        .await;
+    metrics::media_run(
+        FFMPEG_COMMAND,
+        started.elapsed(),
+        ffmpeg.as_ref().is_ok_and(|output| output.status.success()));
+    let ffmpeg = ffmpeg
         .map_err(|e| {
             AudioStreamError::Fail(if e.kind() == ErrorKind::NotFound {
-                format!("could not find executable '{}' on path", FFMPEG_COMMAND).into()
+                format!("could not find executable '{}' on path", config.ffmpeg).into()
             } else {
                 Box::new(e)
             })
         })?;
+
+    if !ffmpeg.status.success() {
+        return Err(AudioStreamError::Fail(
+            format!(
+                "{} failed with non-zero status code: {}",
+                FFMPEG_COMMAND,
+                std::str::from_utf8(&ffmpeg.stderr[..]).unwrap_or("<no error message>")
+            )
+            .into(),
+        ));
+    }
     meta.audio_ext.replace("webm".to_string());
     Ok((ffmpeg_output, meta))
 }
 
+/// Reads the metadata of `url` without downloading it.
+#[cfg_attr(not(feature = "dashboard"), allow(dead_code))]
+pub async fn fetch_output(url: &str) -> Result<Output, AudioStreamError> {
+    let config = config::get();
+    let _permit = DOWNLOADS.acquire().await.expect("downloads are never closed");
+    let started = Instant::now();
+    let ytdl = Command::new(&config.yt_dlp)
+        // the same format as `ytdl_optioned`, so that `url` of the output is set
+        .args(["-j", url, "-f", config.ytdl_format.as_str(), "--no-playlist"])
+        .output()
+        .await;
+    metrics::media_run(
+        YOUTUBE_DL_COMMAND,
+        started.elapsed(),
+        ytdl.as_ref().is_ok_and(|output| output.status.success()));
+    let ytdl = ytdl
+        .map_err(|e| {
+            AudioStreamError::Fail(if e.kind() == ErrorKind::NotFound {
+                format!("could not find executable '{}' on path", config.yt_dlp).into()
+            } else {
+                Box::new(e)
+            })
+        })?;
+
+    if !ytdl.status.success() {
+        return Err(AudioStreamError::Fail(
+            format!(
+                "{} failed with non-zero status code: {}",
+                YOUTUBE_DL_COMMAND,
+                std::str::from_utf8(&ytdl.stderr[..]).unwrap_or("<no error message>")
+            )
+            .into(),
+        ));
+    }
+
+    ytdl.stdout
+        .split(|&b| b == b'\n')
+        .find(|line| !line.is_empty())
+        .ok_or_else(|| AudioStreamError::Fail(format!("no results found for '{url}'").into()))
+        .and_then(|line| serde_json::from_slice(line).map_err(|e| AudioStreamError::Fail(Box::new(e))))
+}
+
+/// Whether yt-dlp already downloaded `url`, so it will not fetch it again.
+async fn is_downloaded(url: &str) -> bool {
+    let Ok(mut entries) = tokio::fs::read_dir(&config::get().cache_dir).await else {
+        return false;
+    };
+    let prefix = format!("{url}.");
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Files in the cache and their total size in bytes.
+pub async fn cache_usage() -> (usize, u64) {
+    let Ok(mut entries) = tokio::fs::read_dir(&config::get().cache_dir).await else {
+        return (0, 0);
+    };
+    let (mut count, mut bytes) = (0, 0);
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if let Ok(metadata) = entry.metadata().await {
+            if metadata.is_file() {
+                count += 1;
+                bytes += metadata.len();
+            }
+        }
+    }
+    (count, bytes)
+}
+
+/// Removes the download and the clips of `url`, so that preparing it again starts from scratch.
+pub async fn evict(url: &str) {
+    let Ok(mut entries) = tokio::fs::read_dir(&config::get().cache_dir).await else {
+        return;
+    };
+    let prefixes = [format!("{url}."), format!("{url}_full."), format!("{url}_cut.")];
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if prefixes.iter().any(|prefix| name.starts_with(prefix)) {
+            if let Err(why) = tokio::fs::remove_file(entry.path()).await {
+                warn!("Failed to remove {:?}: {:?}", entry.path(), why);
+            }
+        }
+    }
+}